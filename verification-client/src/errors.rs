@@ -12,12 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use conjure::value::ser;
 use conjure::value::ConjureValue;
 use hyper::StatusCode;
 use serde_json;
 use std::fmt::Display;
 use test_spec::EndpointName;
 
+const MAX_DISPLAYED_VALUE_LENGTH: usize = 4096;
+
 #[derive(ErrorType)]
 #[error_type(namespace = "ConjureVerificationClient")]
 pub enum VerificationError {
@@ -47,6 +50,20 @@ pub enum VerificationError {
         cause: String,
     },
     #[error_type(code = "FailedPrecondition")]
+    JsonCborMismatch {
+        #[error_type(safe)]
+        json_body_conjure: String,
+        #[error_type(safe)]
+        cbor_body_conjure: String,
+    },
+    #[error_type(code = "FailedPrecondition")]
+    UnstableResponse {
+        #[error_type(safe)]
+        first_body_conjure: String,
+        #[error_type(safe)]
+        response_body_conjure: String,
+    },
+    #[error_type(code = "FailedPrecondition")]
     CouldNotParseServerResponse {
         #[error_type(safe)]
         response_body: String,
@@ -99,7 +116,27 @@ impl VerificationError {
         }
     }
 
+    pub fn json_cbor_mismatch(
+        json_body: &ConjureValue,
+        cbor_body: &ConjureValue,
+    ) -> VerificationError {
+        VerificationError::JsonCborMismatch {
+            json_body_conjure: VerificationError::display_conjure_value(json_body),
+            cbor_body_conjure: VerificationError::display_conjure_value(cbor_body),
+        }
+    }
+
+    pub fn unstable_response(
+        first_body: &ConjureValue,
+        response_body: &ConjureValue,
+    ) -> VerificationError {
+        VerificationError::UnstableResponse {
+            first_body_conjure: VerificationError::display_conjure_value(first_body),
+            response_body_conjure: VerificationError::display_conjure_value(response_body),
+        }
+    }
+
     fn display_conjure_value(value: &ConjureValue) -> String {
-        format!("{:?}", value)
+        ser::to_display_string(value, MAX_DISPLAYED_VALUE_LENGTH)
     }
 }
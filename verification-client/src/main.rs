@@ -50,6 +50,8 @@ extern crate serde_yaml;
 extern crate typed_headers;
 extern crate zipkin;
 
+#[cfg(test)]
+extern crate serde_cbor;
 #[cfg(test)]
 extern crate tokio;
 #[cfg(test)]
@@ -59,8 +61,12 @@ extern crate url;
 extern crate pretty_assertions;
 
 use conjure::ir::Conjure;
+use conjure::value::double::IntegerDoublePolicy;
+use conjure::value::profile::StrictnessProfile;
 use conjure_verification_common::conjure;
+use conjure_verification_common::more_serde_cbor;
 use conjure_verification_common::more_serde_json;
+use conjure_verification_common::ndjson;
 use conjure_verification_common::type_mapping;
 use conjure_verification_common::type_mapping::return_type;
 use conjure_verification_common::type_mapping::ServiceTypeMapping;
@@ -71,8 +77,9 @@ pub use conjure_verification_http_server::*;
 use futures::{future, Future};
 use handler::HttpService;
 use hyper::Server;
-use resource::VerificationClientResource;
+use resource::{SchemaVersionHeader, VerificationClientResource};
 use router::Router;
+use std::collections::HashSet;
 use std::env;
 use std::env::VarError;
 use std::fs::File;
@@ -80,6 +87,7 @@ use std::net::SocketAddr;
 use std::path::Path;
 use std::process;
 use std::sync::Arc;
+use test_spec::EndpointName;
 use test_spec::TestCases;
 
 mod errors;
@@ -92,8 +100,8 @@ mod test;
 fn main() {
     pretty_env_logger::init();
     // TODO use clap for arg parsing
-    let args = &env::args().collect::<Vec<_>>()[..];
-    if args.iter().any(|x| x == "--help") {
+    let (flags, args): (Vec<_>, Vec<_>) = env::args().partition(|arg| arg.starts_with("--"));
+    if flags.iter().any(|x| x == "--help") {
         print_usage(&args[0]);
         process::exit(0);
     }
@@ -103,6 +111,68 @@ fn main() {
         process::exit(1);
     }
 
+    // Verify servers strictly by default, leniency is opt-in. Tests rely on this being the default.
+    let mut profile = StrictnessProfile::default();
+    let mut cbor_equivalence = false;
+    let mut forbid_null_optionals = false;
+    let mut strict_enum_casing = false;
+    let mut integer_doubles = IntegerDoublePolicy::default();
+    let mut ndjson = false;
+    let mut logged_endpoints = HashSet::new();
+    let mut repeat = 1;
+    let mut schema_version_header = None;
+    for flag in &flags {
+        if flag.starts_with(PROFILE_FLAG) {
+            match flag[PROFILE_FLAG.len()..].parse() {
+                Ok(p) => profile = p,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag == CBOR_EQUIVALENCE_FLAG {
+            cbor_equivalence = true;
+        } else if flag == FORBID_NULL_OPTIONALS_FLAG {
+            forbid_null_optionals = true;
+        } else if flag == STRICT_ENUM_CASING_FLAG {
+            strict_enum_casing = true;
+        } else if flag.starts_with(INTEGER_DOUBLES_FLAG) {
+            match flag[INTEGER_DOUBLES_FLAG.len()..].parse() {
+                Ok(policy) => integer_doubles = policy,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag == NDJSON_FLAG {
+            ndjson = true;
+        } else if flag.starts_with(LOG_ENDPOINTS_FLAG) {
+            logged_endpoints.extend(
+                flag[LOG_ENDPOINTS_FLAG.len()..]
+                    .split(',')
+                    .filter(|endpoint| !endpoint.is_empty())
+                    .map(EndpointName::new),
+            );
+        } else if flag.starts_with(REPEAT_FLAG) {
+            match flag[REPEAT_FLAG.len()..].parse::<usize>() {
+                Ok(n) if n > 0 => repeat = n,
+                _ => invalid_flag(&args[0], flag),
+            }
+        } else if flag.starts_with(SCHEMA_VERSION_HEADER_FLAG) {
+            match flag[SCHEMA_VERSION_HEADER_FLAG.len()..].parse::<SchemaVersionHeader>() {
+                Ok(header) => schema_version_header = Some(header),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else {
+            eprintln!("Unknown flag: {}", flag);
+            print_usage(&args[0]);
+            process::exit(1);
+        }
+    }
+
     let port = match env::var("PORT") {
         Ok(port) => port.parse().unwrap(),
         Err(VarError::NotPresent) => 8000,
@@ -125,10 +195,20 @@ fn main() {
         return_type,
     )];
 
-    let resource = Arc::new(VerificationClientResource::new(
-        test_cases.server.into(),
-        type_mapping::resolve_types(&ir, &services_mapping).into(),
-    ));
+    let resource = Arc::new(
+        VerificationClientResource::new(
+            test_cases.server.into(),
+            type_mapping::resolve_types(&ir, &services_mapping).into(),
+            profile,
+        ).with_cbor_equivalence(cbor_equivalence)
+        .with_forbid_null_optionals(forbid_null_optionals)
+        .with_strict_enum_casing(strict_enum_casing)
+        .with_integer_doubles(integer_doubles)
+        .with_ndjson(ndjson)
+        .with_logged_endpoints(logged_endpoints)
+        .with_repeat(repeat)
+        .with_schema_version_header(schema_version_header),
+    );
     let mut builder = router::Router::builder();
     {
         let ref mut binder = Binder::new(resource.clone(), &mut builder, "");
@@ -139,9 +219,29 @@ fn main() {
     start_server(router, port);
 }
 
+const PROFILE_FLAG: &str = "--profile=";
+const CBOR_EQUIVALENCE_FLAG: &str = "--cbor-equivalence";
+const FORBID_NULL_OPTIONALS_FLAG: &str = "--forbid-null-optionals";
+const STRICT_ENUM_CASING_FLAG: &str = "--strict-enum-casing";
+const INTEGER_DOUBLES_FLAG: &str = "--integer-doubles=";
+const NDJSON_FLAG: &str = "--ndjson";
+const LOG_ENDPOINTS_FLAG: &str = "--log-endpoints=";
+const REPEAT_FLAG: &str = "--repeat=";
+const SCHEMA_VERSION_HEADER_FLAG: &str = "--schema-version-header=";
+
+/// Exits after printing the usage, for flags whose value can't be used.
+fn invalid_flag(arg0: &str, flag: &str) -> ! {
+    eprintln!("Invalid flag: {}", flag);
+    print_usage(arg0);
+    process::exit(1);
+}
+
 fn print_usage(arg0: &str) {
     eprintln!(
-        "Usage: {} <client-test-cases.json> <verification-api.conjure.json>",
+        "Usage: {} [--profile=client|server] [--cbor-equivalence] [--forbid-null-optionals] \
+         [--strict-enum-casing] [--integer-doubles=lenient|strict] [--ndjson] \
+         [--log-endpoints=<endpoint>,...] [--repeat=<n>] [--schema-version-header=<name>:<value>] \
+         <client-test-cases.json> <verification-api.conjure.json>",
         arg0
     );
 }
@@ -12,22 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::iter;
+use std::str::FromStr;
 use std::string::ToString;
 
 use either::{Either, Left, Right};
-use hyper::header::HeaderValue;
 use hyper::header::ACCEPT;
+use hyper::header::{HeaderName, HeaderValue};
 use hyper::Method;
 use hyper::StatusCode;
 use mime::APPLICATION_JSON;
+use mime::Mime;
 use mime::APPLICATION_OCTET_STREAM;
 use serde_json;
 use typed_headers::{ContentType, HeaderMapExt};
 use zipkin::Endpoint;
 use zipkin::Tracer;
 
-use conjure::resolved_type::ResolvedType;
+use conjure::resolved_type::{ListType, ResolvedType, SetType};
+use conjure::value::de::ProfiledType;
+use conjure::value::double::IntegerDoublePolicy;
+use conjure::value::profile::StrictnessProfile;
 use conjure::value::*;
 use conjure_verification_common::type_mapping::ParamTypes;
 use conjure_verification_common::type_mapping::TestType;
@@ -44,9 +50,13 @@ use conjure_verification_http_client::request::RequestBuilder;
 use conjure_verification_http_client::user_agent::Agent;
 use conjure_verification_http_client::user_agent::UserAgent;
 use conjure_verification_http_client::Client;
+use conjure_verification_http_client::RemoteError;
+use conjure_verification_http_client::Response as ClientResponse;
 use conjure_verification_http_server::RouteWithOptions;
 use errors::*;
+use more_serde_cbor;
 use more_serde_json;
+use ndjson;
 use test_spec::*;
 
 use self::client_config::ServiceConfig;
@@ -55,11 +65,54 @@ use self::client_config::ServiceDiscoveryConfig;
 lazy_static! {
     static ref USER_AGENT: UserAgent =
         UserAgent::new(Agent::new("conjure-verification-client", "0.0.0"));
+    static ref APPLICATION_CBOR: Mime = "application/cbor".parse().unwrap();
+    static ref APPLICATION_NDJSON: Mime = ndjson::APPLICATION_NDJSON.parse().unwrap();
+}
+
+/// The log target of the verbose logs written for the endpoints passed to
+/// [VerificationClientResource::with_logged_endpoints].
+pub const ENDPOINT_LOG_TARGET: &str = "conjure_verification_client::endpoint";
+
+/// A header versioning the schema of request bodies, e.g. `X-Schema-Version: 2`.
+#[derive(Debug, Clone)]
+pub struct SchemaVersionHeader {
+    name: HeaderName,
+    value: String,
+}
+
+/// Parses `<name>:<value>`.
+impl FromStr for SchemaVersionHeader {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<SchemaVersionHeader, String> {
+        let colon = s
+            .find(':')
+            .ok_or_else(|| format!("Expected '<name>:<value>', got '{}'", s))?;
+        let name = s[..colon]
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid header name '{}'", &s[..colon]))?;
+        let value = s[colon + 1..].trim();
+        HeaderValue::from_str(value).map_err(|_| format!("Invalid header value '{}'", value))?;
+        Ok(SchemaVersionHeader {
+            name,
+            value: value.to_string(),
+        })
+    }
 }
 
 pub struct VerificationClientResource {
     test_cases: Box<ServerTestCases>,
     param_types: Box<ParamTypes>,
+    profile: StrictnessProfile,
+    cbor_equivalence: bool,
+    forbid_null_optionals: bool,
+    strict_enum_casing: bool,
+    integer_doubles: IntegerDoublePolicy,
+    ndjson: bool,
+    logged_endpoints: HashSet<EndpointName>,
+    repeat: usize,
+    schema_version_header: Option<SchemaVersionHeader>,
 }
 
 #[derive(ConjureDeserialize, ConjureSerialize, Debug)]
@@ -73,13 +126,95 @@ impl VerificationClientResource {
     pub fn new(
         test_cases: Box<ServerTestCases>,
         param_types: Box<ParamTypes>,
+        profile: StrictnessProfile,
     ) -> VerificationClientResource {
         VerificationClientResource {
             test_cases,
             param_types,
+            profile,
+            cbor_equivalence: false,
+            forbid_null_optionals: false,
+            strict_enum_casing: false,
+            integer_doubles: IntegerDoublePolicy::default(),
+            ndjson: false,
+            logged_endpoints: HashSet::new(),
+            repeat: 1,
+            schema_version_header: None,
         }
     }
 
+    /// Additionally requests every positive test case as CBOR, and checks that the server encodes
+    /// the same Conjure value it returned as JSON.
+    pub fn with_cbor_equivalence(mut self, cbor_equivalence: bool) -> VerificationClientResource {
+        self.cbor_equivalence = cbor_equivalence;
+        self
+    }
+
+    /// Rejects responses containing an explicit `null` for an optional field, which servers must
+    /// omit instead.
+    pub fn with_forbid_null_optionals(
+        mut self,
+        forbid_null_optionals: bool,
+    ) -> VerificationClientResource {
+        self.forbid_null_optionals = forbid_null_optionals;
+        self
+    }
+
+    /// Rejects responses containing an enum value that matches a declared value only when ignoring
+    /// case, e.g. `apple` for `APPLE`, rather than treating it as an unknown value.
+    pub fn with_strict_enum_casing(
+        mut self,
+        strict_enum_casing: bool,
+    ) -> VerificationClientResource {
+        self.strict_enum_casing = strict_enum_casing;
+        self
+    }
+
+    /// Sets what happens to integers in responses that can't be represented exactly as the doubles
+    /// they're deserialized into.
+    pub fn with_integer_doubles(
+        mut self,
+        integer_doubles: IntegerDoublePolicy,
+    ) -> VerificationClientResource {
+        self.integer_doubles = integer_doubles;
+        self
+    }
+
+    /// Additionally accepts list and set responses streamed as newline-delimited JSON, parsing
+    /// each line as an element.
+    pub fn with_ndjson(mut self, ndjson: bool) -> VerificationClientResource {
+        self.ndjson = ndjson;
+        self
+    }
+
+    /// Logs every request sent to the server under test for the given endpoints, along with its
+    /// response, at info level under [ENDPOINT_LOG_TARGET].
+    pub fn with_logged_endpoints(
+        mut self,
+        logged_endpoints: HashSet<EndpointName>,
+    ) -> VerificationClientResource {
+        self.logged_endpoints = logged_endpoints;
+        self
+    }
+
+    /// Requests every positive test case `repeat` times, and checks that the server returns the
+    /// same Conjure value every time. Values below 1 are treated as 1.
+    pub fn with_repeat(mut self, repeat: usize) -> VerificationClientResource {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Sends the header with every body test case, and additionally checks that the server under
+    /// test rejects each positive test case with a client error when the header is missing or has
+    /// the wrong value.
+    pub fn with_schema_version_header(
+        mut self,
+        schema_version_header: Option<SchemaVersionHeader>,
+    ) -> VerificationClientResource {
+        self.schema_version_header = schema_version_header;
+        self
+    }
+
     fn run_test_case(&self, request: &mut Request) -> Result<impl IntoResponse> {
         let client_request: ClientRequest = request.body()?;
 
@@ -108,44 +243,128 @@ impl VerificationClientResource {
         let endpoint = &client_request.endpoint_name;
 
         let client = VerificationClientResource::construct_client(&client_request.base_url)?;
-        let mut builder = client.post("/body/:endpoint");
-        builder.param("endpoint", &endpoint.0);
-        builder.headers_mut().insert(
-            ACCEPT,
-            HeaderValue::from_static("*/*; q=0.5, application/json"),
-        );
+        if self.logged_endpoints.contains(endpoint) {
+            let body = match test_case {
+                Left(ref positive) => &positive.0,
+                Right(ref negative) => &negative.0,
+            };
+            info!(
+                target: ENDPOINT_LOG_TARGET,
+                "{} test case {}: sending body {}", endpoint, client_request.test_case, body
+            );
+        }
         match test_case {
             Left(positive) => {
-                self.check_positive_test_case(client_request, &endpoint, &mut builder, positive)?;
+                self.check_schema_version_required(client_request, &client, &positive.0)?;
+                let mut first_body = None;
+                for _ in 0..self.repeat.max(1) {
+                    let body = self.check_positive_test_case(
+                        client_request,
+                        &endpoint,
+                        &mut self.test_case_request(&client, endpoint),
+                        positive.clone(),
+                        first_body.as_ref(),
+                    )?;
+                    first_body.get_or_insert(body);
+                }
             }
 
             Right(negative) => {
-                VerificationClientResource::check_negative_test_case(&mut builder, negative)?;
+                let mut builder = self.test_case_request(&client, endpoint);
+                self.check_negative_test_case(client_request, &mut builder, negative)?;
             }
         };
         Ok(NoContent)
     }
 
+    /// A request for a body test case of the given endpoint from the server under test.
+    fn test_case_request<'a>(
+        &self,
+        client: &'a Client,
+        endpoint: &EndpointName,
+    ) -> RequestBuilder<'a> {
+        let mut builder = client.post("/body/:endpoint");
+        builder.param("endpoint", &endpoint.0);
+        let accept = if self.ndjson {
+            "*/*; q=0.5, application/json, application/x-ndjson"
+        } else {
+            "*/*; q=0.5, application/json"
+        };
+        builder
+            .headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static(accept));
+        if let Some(ref header) = self.schema_version_header {
+            builder
+                .headers_mut()
+                .insert(header.name.clone(), HeaderValue::from_str(&header.value).unwrap());
+        }
+        builder
+    }
+
+    /// Fails unless the server under test rejects the body when the schema version header is
+    /// missing or has the wrong value.
+    fn check_schema_version_required(
+        &self,
+        client_request: &ClientRequest,
+        client: &Client,
+        body: &str,
+    ) -> Result<()> {
+        let header = match self.schema_version_header {
+            Some(ref header) => header,
+            None => return Ok(()),
+        };
+        let wrong_value = format!("{}-unsupported", header.value);
+        for &(description, value) in &[("a missing", None), ("the wrong", Some(&wrong_value))] {
+            let mut builder = self.test_case_request(client, &client_request.endpoint_name);
+            match value {
+                Some(value) => {
+                    builder
+                        .headers_mut()
+                        .insert(header.name.clone(), HeaderValue::from_str(value).unwrap());
+                }
+                None => {
+                    builder.headers_mut().remove(&header.name);
+                }
+            }
+            // The client turns error responses into errors, so look for their status there.
+            let status = match builder.body(BytesBody::new(body, APPLICATION_JSON)).send() {
+                Ok(response) => {
+                    self.log_response(client_request, &response);
+                    response.status()
+                }
+                Err(e) => match e.cause().downcast_ref::<RemoteError>() {
+                    Some(remote_error) => *remote_error.status(),
+                    None => return Err(VerificationClientResource::connection_error(e)),
+                },
+            };
+            if !status.is_client_error() {
+                return Err(Error::new_safe(
+                    format!(
+                        "Unexpected response to a request with {} schema version header, \
+                         expected client error",
+                        description
+                    ),
+                    VerificationError::UnexpectedResponseCode { code: status },
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn check_positive_test_case(
         &self,
         client_request: &ClientRequest,
         endpoint: &EndpointName,
         builder: &mut RequestBuilder,
         positive: AutoDeserializePositiveTest,
-    ) -> Result<()> {
+        first_body: Option<&ConjureValue>,
+    ) -> Result<ConjureValue> {
         let test_body_str = positive.0;
         let response = builder
             .body(BytesBody::new(test_body_str.as_str(), APPLICATION_JSON))
             .send()
-            .map_err(|e| {
-                // Unpack error cause to expose it to user.
-                let cause = e.cause().to_string();
-                // TODO format error cause nicely
-                Error::new_safe(
-                    "Failed to connect to server under test",
-                    VerificationError::ServerUnderTestConnectionError { cause },
-                )
-            })?;
+            .map_err(VerificationClientResource::connection_error)?;
+        self.log_response(client_request, &response);
 
         let response_status = response.status();
         if !response_status.is_success() {
@@ -178,15 +397,20 @@ impl VerificationClientResource {
         {
             debug!("Accepting 204 response to empty test case {{testCase: {}, endpoint: {}, testCaseContents: {}}}",
                    client_request.test_case, client_request.endpoint_name, test_body_str);
-            return Ok(());
+            VerificationClientResource::check_stable(first_body, &expected_body)?;
+            return Ok(expected_body);
         }
 
         // At this point, we have concluded we don't expect a 204.
-        // Thus, we expect a 200 with either OCTET_STREAM or APPLICATION_JSON.
+        // Thus, we expect a 200 with either OCTET_STREAM or APPLICATION_JSON, or NDJSON if enabled.
         // Note: we MUST check this before calling .body(), which will fail if there's no content type.
+        let mut expected_content_types = vec![APPLICATION_JSON, APPLICATION_OCTET_STREAM];
+        if self.ndjson {
+            expected_content_types.push(APPLICATION_NDJSON.clone());
+        }
         VerificationClientResource::assert_content_type(
             &content_type,
-            &mut vec![APPLICATION_JSON, APPLICATION_OCTET_STREAM]
+            &mut expected_content_types
                 .into_iter()
                 .map(|mime| Some(ContentType(mime))),
         )?;
@@ -198,9 +422,22 @@ impl VerificationClientResource {
         if content_type.unwrap() == ContentType(APPLICATION_JSON) {
             response_body_value = response.body()?;
             response_body = VerificationClientResource::try_parse_response_body(
-                conjure_type,
+                self.profiled(conjure_type),
                 &response_body_value,
             )?;
+        } else if content_type.unwrap() == ContentType(APPLICATION_NDJSON.clone()) {
+            let mut raw_body = response.raw_body()?;
+            let mut bytes: Vec<u8> = Vec::new();
+            raw_body
+                .0
+                .read_to_end(&mut bytes)
+                .map_err(|e| Error::new_safe(e, VerificationError::ClientIo))?;
+            response_body_value =
+                serde_json::Value::String(String::from_utf8_lossy(&bytes).into_owned());
+            response_body = VerificationClientResource::try_parse_ndjson_response_body(
+                self.profiled(conjure_type),
+                &bytes,
+            )?;
         } else {
             let mut raw_body = response.raw_body()?;
             let mut result: Vec<u8> = Vec::new();
@@ -212,6 +449,18 @@ impl VerificationClientResource {
                 ConjureValue::Primitive(ConjurePrimitiveValue::Binary(Binary(result.to_vec())))
         }
 
+        if self.logged_endpoints.contains(endpoint) {
+            info!(
+                target: ENDPOINT_LOG_TARGET,
+                "{} test case {}: received body {}",
+                endpoint,
+                client_request.test_case,
+                response_body_value
+            );
+        }
+
+        VerificationClientResource::check_stable(first_body, &response_body)?;
+
         // Compare response_body with what the test case says we sent
         if response_body != expected_body {
             let error = "Body didn't match expected Conjure value";
@@ -227,11 +476,122 @@ impl VerificationClientResource {
             ));
         }
 
+        if self.cbor_equivalence && content_type.unwrap() == ContentType(APPLICATION_JSON) {
+            self.check_cbor_equivalence(builder, &test_body_str, conjure_type, &response_body)?;
+        }
+
+        Ok(response_body)
+    }
+
+    /// Fails if a repeated request got a different body than the first one.
+    fn check_stable(first_body: Option<&ConjureValue>, response_body: &ConjureValue) -> Result<()> {
+        match first_body {
+            Some(first_body) if first_body != response_body => Err(Error::new_safe(
+                "Repeated request got a different body",
+                VerificationError::unstable_response(first_body, response_body),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Repeats the request asking for CBOR, and checks that the server encodes the same value as
+    /// it did in its JSON response.
+    fn check_cbor_equivalence(
+        &self,
+        builder: &mut RequestBuilder,
+        test_body_str: &str,
+        conjure_type: &ResolvedType,
+        json_body: &ConjureValue,
+    ) -> Result<()> {
+        builder
+            .headers_mut()
+            .insert(ACCEPT, HeaderValue::from_static("application/cbor"));
+        let response = builder
+            .body(BytesBody::new(test_body_str, APPLICATION_JSON))
+            .send()
+            .map_err(VerificationClientResource::connection_error)?;
+
+        let response_status = response.status();
+        if !response_status.is_success() {
+            return Err(Error::new_safe(
+                "Wasn't successful",
+                VerificationError::UnexpectedResponseCode {
+                    code: response_status,
+                },
+            ));
+        }
+
+        let content_type = response
+            .headers()
+            .typed_get::<ContentType>()
+            .map_err(Error::internal_safe)?;
+        VerificationClientResource::assert_content_type(
+            &content_type,
+            &mut iter::once(Some(ContentType(APPLICATION_CBOR.clone()))),
+        )?;
+
+        let mut raw_body = response.raw_body()?;
+        let mut bytes: Vec<u8> = Vec::new();
+        raw_body
+            .0
+            .read_to_end(&mut bytes)
+            .map_err(|e| Error::new_safe(e, VerificationError::ClientIo))?;
+        let cbor_body =
+            more_serde_cbor::from_slice(self.profiled(conjure_type), &bytes).map_err(|e| {
+                let error_message = format!("{}", e);
+                Error::new_safe(
+                    e,
+                    VerificationError::CouldNotParseServerResponse {
+                        response_body: format!("{:?}", bytes),
+                        cause: error_message,
+                    },
+                )
+            })?;
+
+        if cbor_body != *json_body {
+            return Err(Error::new_safe(
+                "CBOR body didn't match JSON body",
+                VerificationError::json_cbor_mismatch(json_body, &cbor_body),
+            ));
+        }
         Ok(())
     }
 
+    /// Logs the status and headers of a response if its endpoint is logged.
+    fn log_response(&self, client_request: &ClientRequest, response: &ClientResponse) {
+        if self.logged_endpoints.contains(&client_request.endpoint_name) {
+            info!(
+                target: ENDPOINT_LOG_TARGET,
+                "{} test case {}: received status {}, headers {:?}",
+                client_request.endpoint_name,
+                client_request.test_case,
+                response.status(),
+                response.headers()
+            );
+        }
+    }
+
+    /// The given type, deserialized the way this resource reads server responses.
+    fn profiled<'a>(&self, conjure_type: &'a ResolvedType) -> ProfiledType<'a> {
+        conjure_type
+            .with_profile(self.profile)
+            .with_forbid_null_optionals(self.forbid_null_optionals)
+            .with_strict_enum_casing(self.strict_enum_casing)
+            .with_integer_doubles(self.integer_doubles)
+    }
+
+    fn connection_error(e: Error) -> Error {
+        // Unpack error cause to expose it to user.
+        let cause = e.cause().to_string();
+        // TODO format error cause nicely
+        Error::new_safe(
+            "Failed to connect to server under test",
+            VerificationError::ServerUnderTestConnectionError { cause },
+        )
+    }
+
     fn try_parse_response_body(
-        conjure_type: &ResolvedType,
+        conjure_type: ProfiledType,
         response_body_value: &serde_json::Value,
     ) -> Result<ConjureValue> {
         conjure_type.deserialize(response_body_value).map_err(|e| {
@@ -246,6 +606,39 @@ impl VerificationClientResource {
         })
     }
 
+    /// Parses a newline-delimited JSON response as the list or set of the values on its lines.
+    fn try_parse_ndjson_response_body(
+        conjure_type: ProfiledType,
+        bytes: &[u8],
+    ) -> Result<ConjureValue> {
+        let parse_error = |e: serde_json::Error| {
+            let error_message = format!("{}", e);
+            Error::new_safe(
+                e,
+                VerificationError::CouldNotParseServerResponse {
+                    response_body: String::from_utf8_lossy(bytes).into_owned(),
+                    cause: error_message,
+                },
+            )
+        };
+        match conjure_type.type_ {
+            ResolvedType::List(ListType { item_type }) => {
+                let values = ndjson::from_slice(conjure_type.child(item_type), bytes);
+                values.map(ConjureValue::List).map_err(parse_error)
+            }
+            ResolvedType::Set(SetType { item_type }) => {
+                let values = ndjson::set_from_slice(conjure_type.child(item_type), bytes);
+                values.map(ConjureValue::Set).map_err(parse_error)
+            }
+            _ => Err(Error::new_safe(
+                "Only lists and sets can be streamed as NDJSON",
+                VerificationError::UnexpectedContentType {
+                    content_type: APPLICATION_NDJSON.to_string(),
+                },
+            )),
+        }
+    }
+
     /// Checks whether the given [ConjureValue] is an "empty container", i.e. it can be deserialized
     /// from a NO_CONTENT response.
     fn is_empty_container(value: &ConjureValue) -> bool {
@@ -259,12 +652,15 @@ impl VerificationClientResource {
     }
 
     fn check_negative_test_case(
+        &self,
+        client_request: &ClientRequest,
         builder: &mut RequestBuilder,
         negative: AutoDeserializeNegativeTest,
     ) -> Result<()> {
         let response = builder
             .body(BytesBody::new(negative.0, APPLICATION_JSON))
             .send()?;
+        self.log_response(client_request, &response);
         if !response.status().is_client_error() {
             return Err(Error::new_safe(
                 "Unexpected response, expected client error",
@@ -19,9 +19,11 @@ use bytes::Bytes;
 use conjure::ir;
 use conjure::resolved_type::builders::*;
 use conjure::resolved_type::ResolvedType;
+use conjure_verification_common::conjure::value::profile::StrictnessProfile;
+use conjure_verification_common::conjure::value::double::IntegerDoublePolicy;
 use conjure_verification_common::conjure::value::Binary;
 use conjure_verification_common::type_mapping::TestType;
-use conjure_verification_error::{Error, Result};
+use conjure_verification_error::{Code, Error, Result};
 use conjure_verification_http::request::Request;
 use conjure_verification_http::resource::Resource;
 use conjure_verification_http::resource::Route;
@@ -30,6 +32,7 @@ use conjure_verification_http::response::NoContent;
 use conjure_verification_http::response::Response;
 use conjure_verification_http::response::{Body, WriteBody};
 use hyper::header::HeaderValue;
+use hyper::header::{ACCEPT, CONTENT_TYPE};
 use hyper::HeaderMap;
 use hyper::Method;
 use hyper::StatusCode;
@@ -39,8 +42,11 @@ use resource::*;
 use router;
 use router::RouteResult;
 use router::Router;
+use serde_cbor;
 use serde_json;
 use std::collections::HashMap;
+use std::iter;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use test_spec::ServerTestCases;
 use test_spec::{EndpointName, PositiveAndNegativeTestCases};
@@ -170,6 +176,68 @@ fn test_returns_body() {
     );
 }
 
+/// Test that unknown fields in a response are only tolerated with the lenient client profile.
+#[test]
+fn test_unknown_fields() {
+    let conjure_type = || {
+        object_definition(
+            "foo",
+            &[field_definition(
+                "heyo",
+                primitive_type(ir::PrimitiveType::Integer),
+            )],
+        )
+    };
+    let endpoint_name = "returns_unknown_field";
+    let response = |_: &mut Request| Ok(json!({"heyo": 43, "extra": true}));
+
+    let router =
+        setup::setup_simple_auto_positive(json!({"heyo": 43}), endpoint_name, conjure_type());
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        response,
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+
+    let router = setup::setup_profiled_auto_positive(
+        StrictnessProfile::Client,
+        json!({"heyo": 43}),
+        endpoint_name,
+        conjure_type(),
+        |resource| resource,
+    );
+    run_test_case_against_server(&router, TestType::Body, endpoint_name, response, None);
+}
+
+/// Test that special double values are expected in their Conjure string form.
+#[test]
+fn test_returns_infinity() {
+    let conjure_type = primitive_type(ir::PrimitiveType::Double);
+    let endpoint_name = "returns_infinity";
+    let router = setup::setup_simple_auto_positive(json!("Infinity"), endpoint_name, conjure_type);
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |request| {
+            let body = request.body::<serde_json::Value>()?;
+            assert_eq!(body, json!("Infinity"));
+            Ok(body)
+        },
+        None,
+    );
+    // The largest finite double is not a valid substitute for Infinity.
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!(::std::f64::MAX)),
+        Some("ConjureVerificationClient:ConfirmationFailure"),
+    );
+}
+
 /// Test that an empty optional sent as "null" accepts a 204 back.
 #[test]
 fn test_returns_204() {
@@ -208,6 +276,364 @@ fn test_octet_stream() {
     );
 }
 
+/// Test that a server encoding different values as JSON and CBOR is flagged.
+#[test]
+fn test_cbor_equivalence() {
+    let conjure_type = object_definition(
+        "foo",
+        &[field_definition(
+            "heyo",
+            primitive_type(ir::PrimitiveType::Integer),
+        )],
+    );
+    let endpoint_name = "returns_cbor";
+    let router = setup::setup_auto_positive(
+        json!({"heyo": 43}),
+        endpoint_name,
+        conjure_type,
+        |resource| resource.with_cbor_equivalence(true),
+    );
+    let cbor = |value: serde_json::Value| serde_cbor::to_vec(&value).unwrap();
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        move |request| json_or_cbor(request, json!({"heyo": 43}), cbor(json!({"heyo": 43}))),
+        None,
+    );
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        move |request| json_or_cbor(request, json!({"heyo": 43}), cbor(json!({"heyo": 44}))),
+        Some("ConjureVerificationClient:JsonCborMismatch"),
+    );
+    // Bytes after a complete CBOR item are rejected.
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        move |request| {
+            let mut body = cbor(json!({"heyo": 43}));
+            body.push(0);
+            json_or_cbor(request, json!({"heyo": 43}), body)
+        },
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+}
+
+/// Test that present optional collections must be serialized rather than `null`, and that absent
+/// optionals must be omitted when null optionals are forbidden.
+#[test]
+fn test_forbid_null_optionals() {
+    let conjure_type = || {
+        object_definition(
+            "foo",
+            &[
+                field_definition(
+                    "list",
+                    optional_type(list_type(primitive_type(ir::PrimitiveType::Integer))),
+                ),
+                field_definition(
+                    "absent",
+                    optional_type(primitive_type(ir::PrimitiveType::Integer)),
+                ),
+            ],
+        )
+    };
+    let endpoint_name = "returns_optional_collection";
+    let test_body = json!({"list": []});
+    let lenient =
+        setup::setup_simple_auto_positive(test_body.clone(), endpoint_name, conjure_type());
+    let strict = setup::setup_auto_positive(test_body, endpoint_name, conjure_type(), |resource| {
+        resource.with_forbid_null_optionals(true)
+    });
+
+    for router in &[&lenient, &strict] {
+        run_test_case_against_server(
+            router,
+            TestType::Body,
+            endpoint_name,
+            |_| Ok(json!({"list": []})),
+            None,
+        );
+    }
+
+    // An empty list is not an absent optional, whether or not null optionals are allowed.
+    run_test_case_against_server(
+        &lenient,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!({"list": null})),
+        Some("ConjureVerificationClient:ConfirmationFailure"),
+    );
+    run_test_case_against_server(
+        &strict,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!({"list": null})),
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+
+    run_test_case_against_server(
+        &lenient,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!({"list": [], "absent": null})),
+        None,
+    );
+    run_test_case_against_server(
+        &strict,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!({"list": [], "absent": null})),
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+}
+
+/// Test that enum values differing from a declared value only in case are rejected in strict enum
+/// casing mode.
+#[test]
+fn test_strict_enum_casing() {
+    let conjure_type = || enum_definition("Fruit", &["APPLE"]);
+    let endpoint_name = "returns_enum";
+    let lenient = setup::setup_simple_auto_positive(json!("APPLE"), endpoint_name, conjure_type());
+    let strict = setup::setup_auto_positive(
+        json!("APPLE"),
+        endpoint_name,
+        conjure_type(),
+        |resource| resource.with_strict_enum_casing(true),
+    );
+
+    for router in &[&lenient, &strict] {
+        run_test_case_against_server(
+            router,
+            TestType::Body,
+            endpoint_name,
+            |_| Ok(json!("APPLE")),
+            None,
+        );
+    }
+
+    // Either way the value doesn't match, but strict mode points out the casing.
+    run_test_case_against_server(
+        &lenient,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!("apple")),
+        Some("ConjureVerificationClient:ConfirmationFailure"),
+    );
+    run_test_case_against_server(
+        &strict,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!("apple")),
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+}
+
+/// Test that integers that aren't exact doubles are only rejected with the strict policy.
+#[test]
+fn test_strict_integer_doubles() {
+    let conjure_type = || primitive_type(ir::PrimitiveType::Double);
+    let endpoint_name = "returns_double";
+    let test_body = json!(9007199254740992u64);
+    let lenient =
+        setup::setup_simple_auto_positive(test_body.clone(), endpoint_name, conjure_type());
+    let strict = setup::setup_auto_positive(test_body, endpoint_name, conjure_type(), |resource| {
+        resource.with_integer_doubles(IntegerDoublePolicy::Strict)
+    });
+
+    // 2^53 + 1 rounds to the test case's 2^53 as a double.
+    run_test_case_against_server(
+        &lenient,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!(9007199254740993u64)),
+        None,
+    );
+    run_test_case_against_server(
+        &strict,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!(9007199254740993u64)),
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+}
+
+/// Test that a list streamed as newline-delimited JSON is parsed line by line in NDJSON mode.
+#[test]
+fn test_ndjson() {
+    let conjure_type = || list_type(primitive_type(ir::PrimitiveType::Integer));
+    let endpoint_name = "returns_ndjson";
+    let router =
+        setup::setup_auto_positive(json!([1, 2, 3]), endpoint_name, conjure_type(), |resource| {
+            resource.with_ndjson(true)
+        });
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(ndjson_response("1\n2\n3\n")),
+        None,
+    );
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(ndjson_response("1\n2\n")),
+        Some("ConjureVerificationClient:ConfirmationFailure"),
+    );
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(ndjson_response("1\n2\n\"3\"\n")),
+        Some("ConjureVerificationClient:CouldNotParseServerResponse"),
+    );
+
+    let router =
+        setup::setup_simple_auto_positive(json!([1, 2, 3]), endpoint_name, conjure_type());
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(ndjson_response("1\n2\n3\n")),
+        Some("ConjureVerificationClient:UnexpectedContentType"),
+    );
+}
+
+/// Test that repeated requests must get the same value back in repeat mode.
+#[test]
+fn test_repeat() {
+    let conjure_type = || primitive_type(ir::PrimitiveType::Integer);
+    let endpoint_name = "returns_int";
+    let router =
+        setup::setup_auto_positive(json!(5), endpoint_name, conjure_type(), |resource| {
+            resource.with_repeat(3)
+        });
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!(5)),
+        None,
+    );
+
+    // The first response is correct, but the second one isn't.
+    let calls = Arc::new(AtomicUsize::new(0));
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        move |_| Ok(json!(5 + calls.fetch_add(1, Ordering::SeqCst).min(1))),
+        Some("ConjureVerificationClient:UnstableResponse"),
+    );
+}
+
+/// Test that servers must reject bodies without the configured schema version header.
+#[test]
+fn test_schema_version_header() {
+    let conjure_type = || primitive_type(ir::PrimitiveType::Integer);
+    let endpoint_name = "returns_int";
+    let router = setup::setup_auto_positive(json!(5), endpoint_name, conjure_type(), |resource| {
+        resource.with_schema_version_header(Some("X-Schema-Version:2".parse().unwrap()))
+    });
+    let unsupported = || Error::new_safe("unsupported schema version", Code::InvalidArgument);
+
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        move |request| match request.headers().get("X-Schema-Version") {
+            Some(version) if version == "2" => Ok(json!(5)),
+            _ => Err(unsupported()),
+        },
+        None,
+    );
+
+    // Servers that ignore the header accept requests that are missing it.
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        |_| Ok(json!(5)),
+        Some("ConjureVerificationClient:UnexpectedResponseCode"),
+    );
+
+    // Servers that only check the header is present accept the wrong version.
+    run_test_case_against_server(
+        &router,
+        TestType::Body,
+        endpoint_name,
+        move |request| match request.headers().get("X-Schema-Version") {
+            Some(_) => Ok(json!(5)),
+            None => Err(unsupported()),
+        },
+        Some("ConjureVerificationClient:UnexpectedResponseCode"),
+    );
+}
+
+/// Test that verbose request and response logs are only written for the logged endpoints.
+#[test]
+fn test_logged_endpoints() {
+    logs::capture();
+    let conjure_type = || primitive_type(ir::PrimitiveType::Integer);
+    for endpoint_name in &["verbose_endpoint", "quiet_endpoint"] {
+        let logged_endpoints = iter::once(EndpointName::new("verbose_endpoint")).collect();
+        let router =
+            setup::setup_auto_positive(json!(5), endpoint_name, conjure_type(), |resource| {
+                resource.with_logged_endpoints(logged_endpoints)
+            });
+        run_test_case_against_server(
+            &router,
+            TestType::Body,
+            endpoint_name,
+            |_| Ok(json!(5)),
+            None,
+        );
+    }
+
+    let logs = logs::captured();
+    let logged = |prefix: &str| logs.iter().any(|log| log.starts_with(prefix));
+    assert!(logged("verbose_endpoint test case 0: sending body 5"));
+    assert!(logged("verbose_endpoint test case 0: received status 200"));
+    assert!(logged("verbose_endpoint test case 0: received body 5"));
+    assert!(logs.iter().all(|log| !log.contains("quiet_endpoint")));
+}
+
+fn ndjson_response(body: &'static str) -> Response {
+    let mut response = Response::new(StatusCode::OK);
+    response.headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/x-ndjson"),
+    );
+    response.body = Body::Fixed(Bytes::from(body));
+    response
+}
+
+/// Responds with the `cbor` bytes if the request accepts CBOR, and with `json` otherwise.
+fn json_or_cbor(
+    request: &mut Request,
+    json: serde_json::Value,
+    cbor: Vec<u8>,
+) -> Result<Response> {
+    let accepts_cbor = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map_or(false, |accept| accept.contains("application/cbor"));
+    if !accepts_cbor {
+        return json.into_response(request);
+    }
+    let mut response = Response::new(StatusCode::OK);
+    response
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/cbor"));
+    response.body = Body::Fixed(Bytes::from(cbor));
+    Ok(response)
+}
+
 pub struct StreamingResponse(Vec<u8>);
 
 impl WriteBody for StreamingResponse {
@@ -265,6 +691,48 @@ fn run_test_case_against_server<F, R>(
     });
 }
 
+/// Captures the logs written under [ENDPOINT_LOG_TARGET].
+mod logs {
+    use super::*;
+    use log::{self, LevelFilter, Log, Metadata, Record};
+    use std::sync::{Mutex, Once, ONCE_INIT};
+
+    struct CapturingLogger(Mutex<Vec<String>>);
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == ENDPOINT_LOG_TARGET
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                self.0.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    lazy_static! {
+        static ref LOGGER: CapturingLogger = CapturingLogger(Mutex::new(vec![]));
+    }
+
+    static INIT: Once = ONCE_INIT;
+
+    /// Installs the capturing logger, which must be the only logger of the test binary.
+    pub fn capture() {
+        INIT.call_once(|| {
+            log::set_logger(&*LOGGER).unwrap();
+            log::set_max_level(LevelFilter::Info);
+        });
+    }
+
+    /// The messages logged so far.
+    pub fn captured() -> Vec<String> {
+        LOGGER.0.lock().unwrap().clone()
+    }
+}
+
 /// Contains logic for setting up the [VerificationClientResource].
 mod setup {
     use super::*;
@@ -302,7 +770,41 @@ mod setup {
         endpoint_name: &str,
         conjure_type: ResolvedType,
     ) -> Router {
-        setup_routes(|test_cases, param_types| {
+        setup_auto_positive(test_body, endpoint_name, conjure_type, |resource| resource)
+    }
+
+    /// Like [setup_simple_auto_positive], but configures the resource first.
+    pub fn setup_auto_positive<C>(
+        test_body: serde_json::Value,
+        endpoint_name: &str,
+        conjure_type: ResolvedType,
+        configure: C,
+    ) -> Router
+    where
+        C: FnOnce(VerificationClientResource) -> VerificationClientResource,
+    {
+        setup_profiled_auto_positive(
+            StrictnessProfile::default(),
+            test_body,
+            endpoint_name,
+            conjure_type,
+            configure,
+        )
+    }
+
+    /// Like [setup_auto_positive], but reads responses with the given profile instead of the one
+    /// the binary defaults to.
+    pub fn setup_profiled_auto_positive<C>(
+        profile: StrictnessProfile,
+        test_body: serde_json::Value,
+        endpoint_name: &str,
+        conjure_type: ResolvedType,
+        configure: C,
+    ) -> Router
+    where
+        C: FnOnce(VerificationClientResource) -> VerificationClientResource,
+    {
+        setup_routes(profile, configure, |test_cases, param_types| {
             test_cases.auto_deserialize = hashmap!(
                     EndpointName::new(endpoint_name) => PositiveAndNegativeTestCases {
                         positive: vec![test_body.to_string()],
@@ -318,25 +820,32 @@ mod setup {
     }
 
     /// Sets up a router for a [VerificationClientResource] handling the desired server test cases.
-    fn setup_routes<F>(f: F) -> Router
+    fn setup_routes<C, F>(profile: StrictnessProfile, configure: C, f: F) -> Router
     where
+        C: FnOnce(VerificationClientResource) -> VerificationClientResource,
         F: FnOnce(&mut ServerTestCases, &mut ParamTypesBuilder),
     {
         let mut test_cases = ServerTestCases::default();
         let mut param_types = ParamTypesBuilder::default();
         f(&mut test_cases, &mut param_types);
-        let (router, _) = create_resource(test_cases, param_types.build());
+        let (router, _) = create_resource(test_cases, param_types.build(), profile, configure);
         router
     }
 
-    fn create_resource(
+    fn create_resource<C>(
         test_cases: ServerTestCases,
         param_types: ParamTypes,
-    ) -> (Router, Arc<VerificationClientResource>) {
-        let resource = Arc::new(VerificationClientResource::new(
+        profile: StrictnessProfile,
+        configure: C,
+    ) -> (Router, Arc<VerificationClientResource>)
+    where
+        C: FnOnce(VerificationClientResource) -> VerificationClientResource,
+    {
+        let resource = Arc::new(configure(VerificationClientResource::new(
             Box::new(test_cases),
             Box::new(param_types),
-        ));
+            profile,
+        )));
         let mut builder = router::Router::builder();
         {
             let ref mut binder = Binder::new(resource.clone(), &mut builder, "");
@@ -0,0 +1,116 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks for deserializing JSON into a `ConjureValue` of a given `ResolvedType`, which is
+//! what the verifiers spend most of their time doing.
+
+#[macro_use]
+extern crate criterion;
+extern crate conjure_verification_common;
+
+use conjure_verification_common::conjure::ir::PrimitiveType;
+use conjure_verification_common::conjure::resolved_type::builders::*;
+use conjure_verification_common::conjure::resolved_type::ResolvedType;
+use conjure_verification_common::more_serde_json::from_str;
+use criterion::Criterion;
+
+const LIST_LENGTH: usize = 10_000;
+const UNION_DEPTH: usize = 32;
+
+/// An object with a field of each common kind.
+fn object_type() -> ResolvedType {
+    object_definition(
+        "Object",
+        &[
+            field_definition("string", primitive_type(PrimitiveType::String)),
+            field_definition("integer", primitive_type(PrimitiveType::Integer)),
+            field_definition("double", primitive_type(PrimitiveType::Double)),
+            field_definition("uuid", primitive_type(PrimitiveType::Uuid)),
+            field_definition("datetime", primitive_type(PrimitiveType::Datetime)),
+            field_definition(
+                "optional",
+                optional_type(primitive_type(PrimitiveType::String)),
+            ),
+            field_definition(
+                "list",
+                list_type(primitive_type(PrimitiveType::Integer)),
+            ),
+            field_definition(
+                "map",
+                map_type(
+                    primitive_type(PrimitiveType::String),
+                    primitive_type(PrimitiveType::Boolean),
+                ),
+            ),
+            field_definition("enum", enum_definition("Enum", &["ONE", "TWO"])),
+        ],
+    )
+}
+
+const OBJECT_JSON: &str = r#"{
+    "string": "hello",
+    "integer": 1234,
+    "double": "NaN",
+    "uuid": "7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51",
+    "datetime": "2018-07-19T08:11:21Z",
+    "optional": null,
+    "list": [1, 2, 3, 4, 5],
+    "map": {"a": true, "b": false},
+    "enum": "TWO"
+}"#;
+
+/// A union that can nest itself `depth` times.
+fn nested_union_type(depth: usize) -> ResolvedType {
+    (0..depth).fold(primitive_type(PrimitiveType::Integer), |inner, i| {
+        union_definition(
+            &format!("Union{}", i),
+            &[
+                field_definition("int", primitive_type(PrimitiveType::Integer)),
+                field_definition("nested", inner),
+            ],
+        )
+    })
+}
+
+fn nested_union_json(depth: usize) -> String {
+    (1..depth).fold(r#"{"type": "int", "int": 1}"#.to_string(), |inner, _| {
+        format!(r#"{{"type": "nested", "nested": {}}}"#, inner)
+    })
+}
+
+fn object(c: &mut Criterion) {
+    let type_ = object_type();
+    c.bench_function("object", move |b| {
+        b.iter(|| from_str(&type_, OBJECT_JSON).unwrap())
+    });
+}
+
+fn large_list(c: &mut Criterion) {
+    let type_ = list_type(object_type());
+    let json = format!("[{}]", vec![OBJECT_JSON; LIST_LENGTH].join(","));
+    c.bench_function("large_list", move |b| {
+        b.iter(|| from_str(&type_, &json).unwrap())
+    });
+}
+
+fn nested_unions(c: &mut Criterion) {
+    let type_ = nested_union_type(UNION_DEPTH);
+    let json = nested_union_json(UNION_DEPTH);
+    c.bench_function("nested_unions", move |b| {
+        b.iter(|| from_str(&type_, &json).unwrap())
+    });
+}
+
+criterion_group!(benches, object, large_list, nested_unions);
+criterion_main!(benches);
@@ -22,6 +22,7 @@ use conjure::resolved_type::ObjectDefinition;
 use conjure::resolved_type::OptionalType;
 use conjure::resolved_type::ResolvedType;
 use conjure::resolved_type::SetType;
+use conjure::resolved_type::UnionDefinition;
 
 const PACKAGE: &'static str = "com.palantir.package";
 
@@ -42,6 +43,13 @@ pub fn object_definition(name: &str, fields: &[FieldDefinition]) -> ResolvedType
     })
 }
 
+pub fn union_definition(name: &str, variants: &[FieldDefinition]) -> ResolvedType {
+    ResolvedType::Union(UnionDefinition {
+        type_name: type_name(name),
+        union: variants.to_vec(),
+    })
+}
+
 pub fn enum_definition(name: &str, variants: &[&str]) -> ResolvedType {
     ResolvedType::Enum(ir::EnumDefinition {
         type_name: type_name(name),
@@ -20,59 +20,78 @@ use conjure::ir::Type;
 use conjure::ir::TypeDefinition;
 use conjure::resolved_type::ResolvedType;
 use conjure::resolved_type::*;
+use conjure_verification_error::Code;
+use conjure_verification_error::Error;
+use conjure_verification_error::Result;
 
 /// Recursively resolve references and aliases to get to the real types.
+///
+/// Panics if the type is invalid; see [try_resolve_type](fn.try_resolve_type.html).
 pub fn resolve_type(types: &[TypeDefinition], t: &Type) -> ResolvedType {
-    match t {
+    try_resolve_type(types, t).unwrap()
+}
+
+/// Recursively resolve references and aliases to get to the real types.
+///
+/// Fails if a reference isn't defined in `types`, or if a map's key type isn't a primitive or enum.
+pub fn try_resolve_type(types: &[TypeDefinition], t: &Type) -> Result<ResolvedType> {
+    Ok(match t {
         Type::Reference(name) => {
-            // Expect to find this type name in the definitions.
-            let definition = types.iter().find(|def| def.type_name() == name).unwrap();
-            resolve_type_definition(types, definition)
+            let definition = types
+                .iter()
+                .find(|def| def.type_name() == name)
+                .ok_or_else(|| {
+                    Error::new_safe("Referenced type is not defined", Code::InvalidArgument)
+                        .with_safe_param("typeName", format!("{}.{}", name.package, name.name))
+                })?;
+            resolve_type_definition(types, definition)?
         }
         Type::Primitive(primitive) => ResolvedType::Primitive(primitive.clone()),
         Type::Optional(inner) => ResolvedType::Optional(OptionalType {
-            item_type: resolve_type(types, &inner.item_type).into(),
+            item_type: try_resolve_type(types, &inner.item_type)?.into(),
         }),
         Type::List(inner) => ResolvedType::List(ListType {
-            item_type: resolve_type(types, &inner.item_type).into(),
+            item_type: try_resolve_type(types, &inner.item_type)?.into(),
         }),
         Type::Set(inner) => ResolvedType::Set(SetType {
-            item_type: resolve_type(types, &inner.item_type).into(),
+            item_type: try_resolve_type(types, &inner.item_type)?.into(),
         }),
         Type::Map(ir::MapType {
             key_type,
             value_type,
         }) => ResolvedType::Map(MapType {
-            key_type: (match resolve_type(types, &key_type) {
+            key_type: (match try_resolve_type(types, &key_type)? {
                 it @ ResolvedType::Primitive(_) => it,
                 it @ ResolvedType::Enum(_) => it,
-                it => panic!(
-                    "Map key type should be primitive or enum but found: {:?}",
-                    it
-                ),
+                it => {
+                    return Err(Error::new_safe(
+                        "Map key type should be primitive or enum",
+                        Code::InvalidArgument,
+                    ).with_safe_param("keyType", format!("{:?}", it)))
+                }
             }).into(),
-            value_type: resolve_type(types, &value_type).into(),
+            value_type: try_resolve_type(types, &value_type)?.into(),
         }),
-    }
+    })
 }
 
 fn resolve_field_definition(
     types: &[TypeDefinition],
     field_def: &ir::FieldDefinition,
-) -> FieldDefinition {
+) -> Result<FieldDefinition> {
     let &ir::FieldDefinition {
         ref field_name,
         ref type_,
     } = field_def;
-    FieldDefinition {
+    Ok(FieldDefinition {
         field_name: field_name.clone(),
-        type_: resolve_type(types, type_),
-    }
+        type_: try_resolve_type(types, type_)?,
+    })
 }
 
-fn resolve_type_definition(types: &[TypeDefinition], t: &TypeDefinition) -> ResolvedType {
-    match t {
-        TypeDefinition::Alias(alias) => resolve_type(types, &alias.alias),
+fn resolve_type_definition(types: &[TypeDefinition], t: &TypeDefinition) -> Result<ResolvedType> {
+    Ok(match t {
+        TypeDefinition::Alias(alias) => try_resolve_type(types, &alias.alias)?,
         TypeDefinition::Enum(enum_) => ResolvedType::Enum(enum_.clone()),
         TypeDefinition::Object(obj) => ResolvedType::Object(ObjectDefinition {
             type_name: obj.type_name.clone(),
@@ -80,7 +99,7 @@ fn resolve_type_definition(types: &[TypeDefinition], t: &TypeDefinition) -> Reso
                 .fields
                 .iter()
                 .map(|defn| resolve_field_definition(types, defn))
-                .collect(),
+                .collect::<Result<_>>()?,
         }),
         TypeDefinition::Union(union) => ResolvedType::Union(UnionDefinition {
             type_name: union.type_name.clone(),
@@ -88,7 +107,7 @@ fn resolve_type_definition(types: &[TypeDefinition], t: &TypeDefinition) -> Reso
                 .union
                 .iter()
                 .map(|defn| resolve_field_definition(types, defn))
-                .collect(),
+                .collect::<Result<_>>()?,
         }),
-    }
+    })
 }
@@ -0,0 +1,140 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that UUIDs are in the canonical form Conjure sends them in: 32 lowercase hex digits in
+//! hyphenated `8-4-4-4-12` groups, e.g. `7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51`.
+
+use serde::de::{DeserializeSeed, Error, Visitor};
+use serde::Deserializer;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Which spellings of a UUID are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UuidPolicy {
+    /// Accept anything the `uuid` crate parses, including uppercase hex, unhyphenated hex and
+    /// `urn:uuid:` prefixes.
+    Lenient,
+    /// Only accept the canonical form.
+    Canonical,
+}
+
+impl Default for UuidPolicy {
+    fn default() -> UuidPolicy {
+        UuidPolicy::Lenient
+    }
+}
+
+impl FromStr for UuidPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<UuidPolicy, String> {
+        match s {
+            "lenient" => Ok(UuidPolicy::Lenient),
+            "canonical" => Ok(UuidPolicy::Canonical),
+            other => Err(format!(
+                "Unknown uuid policy '{}', expected 'lenient' or 'canonical'",
+                other
+            )),
+        }
+    }
+}
+
+impl UuidPolicy {
+    /// Parses a UUID, checking its spelling according to the policy.
+    pub fn parse(self, s: &str) -> Result<Uuid, String> {
+        if self == UuidPolicy::Canonical && !is_canonical(s) {
+            return Err(format!(
+                "uuid `{}` is not in canonical form, expected lowercase hyphenated hex",
+                s
+            ));
+        }
+        Uuid::parse_str(s).map_err(|e| format!("invalid uuid `{}`: {}", s, e))
+    }
+}
+
+/// Whether the string is a UUID in canonical form.
+pub fn is_canonical(s: &str) -> bool {
+    s.len() == 36 && s.char_indices().all(|(i, c)| match i {
+        8 | 13 | 18 | 23 => c == '-',
+        _ => c.is_digit(16) && !c.is_ascii_uppercase(),
+    })
+}
+
+/// Deserializes a UUID string according to the policy.
+impl<'de> DeserializeSeed<'de> for UuidPolicy {
+    type Value = Uuid;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Uuid, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(UuidVisitor(self))
+    }
+}
+
+struct UuidVisitor(UuidPolicy);
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a uuid string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Uuid, E>
+    where
+        E: Error,
+    {
+        self.0.parse(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CANONICAL: &str = "7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51";
+
+    #[test]
+    fn canonical_form() {
+        let non_canonical = [
+            "7CD6E2F7-A2A6-4B9B-9B0C-0E4A2B6E9A51".to_string(),
+            format!("urn:uuid:{}", CANONICAL),
+            CANONICAL.replace("-", ""),
+        ];
+        let braced = format!("{{{}}}", CANONICAL);
+        let expected: Uuid = CANONICAL.parse().unwrap();
+        let canonical_error = |uuid| {
+            Err(format!(
+                "uuid `{}` is not in canonical form, expected lowercase hyphenated hex",
+                uuid
+            ))
+        };
+
+        assert!(is_canonical(CANONICAL));
+        assert_eq!(UuidPolicy::Lenient.parse(CANONICAL), Ok(expected));
+        assert_eq!(UuidPolicy::Canonical.parse(CANONICAL), Ok(expected));
+        for uuid in &non_canonical {
+            assert!(!is_canonical(uuid), "{}", uuid);
+            assert_eq!(UuidPolicy::Lenient.parse(uuid), Ok(expected), "{}", uuid);
+            assert_eq!(UuidPolicy::Canonical.parse(uuid), canonical_error(uuid));
+        }
+        // The uuid crate doesn't parse braces, so they are rejected either way.
+        assert!(!is_canonical(&braced));
+        assert!(UuidPolicy::Lenient.parse(&braced).is_err());
+        assert_eq!(UuidPolicy::Canonical.parse(&braced), canonical_error(&braced));
+    }
+}
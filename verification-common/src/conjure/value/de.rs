@@ -27,6 +27,9 @@ use conjure::ir::EnumDefinition;
 use conjure::ir::PrimitiveType;
 use conjure::resolved_type::ResolvedType::*;
 use conjure::resolved_type::*;
+use conjure::value::canonical_uuid::UuidPolicy;
+use conjure::value::double::IntegerDoublePolicy;
+use conjure::value::profile::StrictnessProfile;
 use conjure::value::visitors::map::ConjureMapVisitor;
 use conjure::value::visitors::object::ConjureObjectVisitor;
 use conjure::value::visitors::option::ConjureOptionVisitor;
@@ -39,6 +42,9 @@ use serde::de::Visitor;
 use serde::Deserialize;
 use serde::Deserializer;
 
+/// Deserializes with the default (server) [StrictnessProfile].
+///
+/// [StrictnessProfile]: ../profile/enum.StrictnessProfile.html
 impl<'de: 'a, 'a> DeserializeSeed<'de> for &'a ResolvedType {
     type Value = ConjureValue;
 
@@ -46,38 +52,162 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for &'a ResolvedType {
     where
         D: Deserializer<'de>,
     {
-        Ok(match self {
+        self.with_profile(StrictnessProfile::default())
+            .deserialize(deserializer)
+    }
+}
+
+impl ResolvedType {
+    /// Returns a seed that deserializes this type using the given strictness profile.
+    pub fn with_profile(&self, profile: StrictnessProfile) -> ProfiledType {
+        ProfiledType {
+            type_: self,
+            profile,
+            integer_doubles: IntegerDoublePolicy::default(),
+            uuids: UuidPolicy::default(),
+            forbid_null_optionals: false,
+            strict_enum_casing: false,
+            partial: false,
+        }
+    }
+}
+
+/// A [ResolvedType] together with the [StrictnessProfile] used to deserialize it.
+///
+/// [ResolvedType]: ../../resolved_type/enum.ResolvedType.html
+/// [StrictnessProfile]: ../profile/enum.StrictnessProfile.html
+#[derive(Debug, Clone, Copy)]
+pub struct ProfiledType<'a> {
+    pub type_: &'a ResolvedType,
+    pub profile: StrictnessProfile,
+    /// How integer tokens are deserialized into doubles.
+    pub integer_doubles: IntegerDoublePolicy,
+    /// Which spellings of UUIDs are accepted.
+    pub uuids: UuidPolicy,
+    /// Whether an object field whose optional value is absent must be omitted rather than null.
+    pub forbid_null_optionals: bool,
+    /// Whether an enum value that only differs in case from a declared value is rejected rather
+    /// than accepted as unknown.
+    pub strict_enum_casing: bool,
+    /// Whether an object is a partial update, whose missing fields are left out of the result
+    /// rather than defaulted or rejected. Only applies to the outermost value.
+    pub partial: bool,
+}
+
+impl<'a> ProfiledType<'a> {
+    /// Sets how integer tokens that aren't exactly representable as doubles are handled.
+    pub fn with_integer_doubles(
+        mut self,
+        integer_doubles: IntegerDoublePolicy,
+    ) -> ProfiledType<'a> {
+        self.integer_doubles = integer_doubles;
+        self
+    }
+
+    /// Sets which spellings of UUIDs are accepted.
+    pub fn with_uuids(mut self, uuids: UuidPolicy) -> ProfiledType<'a> {
+        self.uuids = uuids;
+        self
+    }
+
+    /// Sets whether object fields with absent optional values must be omitted rather than null.
+    pub fn with_forbid_null_optionals(mut self, forbid_null_optionals: bool) -> ProfiledType<'a> {
+        self.forbid_null_optionals = forbid_null_optionals;
+        self
+    }
+
+    /// Sets whether enum values that match a declared value ignoring case must match its casing.
+    pub fn with_strict_enum_casing(mut self, strict_enum_casing: bool) -> ProfiledType<'a> {
+        self.strict_enum_casing = strict_enum_casing;
+        self
+    }
+
+    /// Sets whether an object is deserialized as a partial update, leaving out its missing fields.
+    pub fn with_partial(mut self, partial: bool) -> ProfiledType<'a> {
+        self.partial = partial;
+        self
+    }
+
+    /// Returns the given type, deserialized using the same settings, except that it's never
+    /// partial.
+    pub fn child<'b>(&self, type_: &'b ResolvedType) -> ProfiledType<'b> {
+        ProfiledType {
+            type_,
+            profile: self.profile,
+            integer_doubles: self.integer_doubles,
+            uuids: self.uuids,
+            forbid_null_optionals: self.forbid_null_optionals,
+            strict_enum_casing: self.strict_enum_casing,
+            partial: false,
+        }
+    }
+}
+
+impl<'de: 'a, 'a> DeserializeSeed<'de> for ProfiledType<'a> {
+    type Value = ConjureValue;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, <D as Deserializer<'de>>::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let profile = self.profile;
+        Ok(match self.type_ {
+            Primitive(PrimitiveType::Any) if profile.allow_null_any() => {
+                ConjureValue::Primitive(ConjurePrimitiveValue::Any(deserializer.deser()?))
+            }
+            Primitive(PrimitiveType::Double) => ConjureValue::Primitive(
+                ConjurePrimitiveValue::Double(self.integer_doubles.deserialize(deserializer)?),
+            ),
+            Primitive(PrimitiveType::Uuid) if self.uuids == UuidPolicy::Canonical => {
+                ConjureValue::Primitive(ConjurePrimitiveValue::Uuid(
+                    self.uuids.deserialize(deserializer)?,
+                ))
+            }
             Primitive(p) => ConjureValue::Primitive(p.deserialize(deserializer)?),
             Optional(OptionalType { item_type }) => ConjureValue::Optional(
                 deserializer
-                    .deserialize_option(ConjureOptionVisitor(&item_type))?
+                    .deserialize_option(ConjureOptionVisitor(self.child(&item_type)))?
                     .map(Box::new),
             ),
-            Object(ObjectDefinition { fields, .. }) => {
-                // TODO(dsanduleac): bubble up the skip_unknown (it's false for servers)
-                ConjureValue::Object(
-                    deserializer.deserialize_map(ConjureObjectVisitor::new(&fields, false))?,
-                )
-            }
-            List(ListType { item_type }) => {
-                ConjureValue::List(deserializer.deserialize_seq(ConjureSeqVisitor(&item_type))?)
-            }
+            Object(ObjectDefinition { fields, .. }) => ConjureValue::Object(
+                deserializer.deserialize_map(ConjureObjectVisitor::new(&fields, self))?,
+            ),
+            List(ListType { item_type }) => ConjureValue::List(
+                deserializer.deserialize_seq(ConjureSeqVisitor(self.child(&item_type)))?,
+            ),
             Set(SetType { ref item_type }) => {
                 ConjureValue::Set(deserializer.deserialize_seq(ConjureSetVisitor {
-                    item_type,
-                    fail_on_duplicates: true,
+                    item_type: self.child(item_type),
+                    fail_on_duplicates: profile.fail_on_set_duplicates(),
                 })?)
             }
             Map(MapType {
                 ref key_type,
                 ref value_type,
             }) => ConjureValue::Map(deserializer.deserialize_map(ConjureMapVisitor {
-                key_type,
-                value_type,
+                key_type: self.child(key_type),
+                value_type: self.child(value_type),
             })?),
-            Enum(enum_def) => ConjureValue::Enum(enum_def.deserialize(deserializer)?),
+            Enum(enum_def) => {
+                let value = enum_def.deserialize(deserializer)?;
+                if let EnumValue::Unknown(ref value) = value {
+                    if self.strict_enum_casing {
+                        let declared = enum_def
+                            .values
+                            .iter()
+                            .find(|declared| declared.value.eq_ignore_ascii_case(value));
+                        if let Some(declared) = declared {
+                            return Err(Error::custom(format_args!(
+                                "enum value `{}` does not match the casing of `{}`",
+                                value, declared.value
+                            )));
+                        }
+                    }
+                }
+                ConjureValue::Enum(value)
+            }
             Union(union_definition) => ConjureValue::Union(
-                deserializer.deserialize_map(ConjureUnionVisitor(&union_definition))?,
+                deserializer.deserialize_map(ConjureUnionVisitor(&union_definition, self))?,
             ),
         })
     }
@@ -119,8 +249,11 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for &'a PrimitiveType {
             PrimitiveType::Datetime => ConjurePrimitiveValue::Datetime(de.deser()?),
             PrimitiveType::Any => {
                 let deser = de.deser()?;
-                // We explicitly don't allow 'null' in any.
-                if deser == ::serde_value::Value::Option(None) {
+                // We explicitly don't allow 'null' in any. Depending on the deserializer, null is
+                // either visited as a unit or as a none.
+                if deser == ::serde_value::Value::Unit
+                    || deser == ::serde_value::Value::Option(None)
+                {
                     return Err(::serde::de::Error::custom("unexpected 'null' for type any"));
                 }
                 ConjurePrimitiveValue::Any(deser)
@@ -185,6 +318,8 @@ impl<'de> Deserialize<'de> for Binary {
 mod test {
     use super::*;
     use conjure::ir::TypeName;
+    use conjure::resolved_type::builders::*;
+    use more_serde_json::from_slice;
     use more_serde_json::from_str;
 
     #[test]
@@ -198,6 +333,165 @@ mod test {
         assert!(from_str(&type_, "null").is_err());
     }
 
+    #[test]
+    fn optional_special_doubles() {
+        use conjure::value::ser::{self, SetOrder};
+        use std::cmp::Ordering;
+
+        let type_ = optional_type(primitive_type(PrimitiveType::Double));
+        let cases = [
+            (r#""NaN""#, Some(::std::f64::NAN)),
+            (r#""Infinity""#, Some(::std::f64::INFINITY)),
+            (r#""-Infinity""#, Some(::std::f64::NEG_INFINITY)),
+            ("null", None),
+        ];
+        for &(json, value) in &cases {
+            let expected = ConjureValue::Optional(
+                value.map(|v| Box::new(ConjureValue::Primitive(ConjurePrimitiveValue::double(v)))),
+            );
+            let value = from_str(&type_, json).unwrap();
+            assert_eq!(value, expected, "{}", json);
+            // NaN is equal to itself once wrapped, so comparisons agree with equality.
+            assert_eq!(value.cmp(&expected), Ordering::Equal, "{}", json);
+            assert_eq!(ser::to_string(&value, SetOrder::default()).unwrap(), json);
+
+            let json_value: ::serde_json::Value = ::serde_json::from_str(json).unwrap();
+            let cbor = ::serde_cbor::to_vec(&json_value).unwrap();
+            let mut deserializer = ::serde_cbor::Deserializer::from_slice(&cbor);
+            assert_eq!(type_.deserialize(&mut deserializer).unwrap(), expected, "{}", json);
+        }
+
+        // Inside objects, absent values are omitted and present ones keep their string form.
+        let object = object_definition("Foo", &[field_definition("bar", type_.clone())]);
+        for json in &["{}", r#"{"bar":"NaN"}"#, r#"{"bar":"-Infinity"}"#] {
+            let value = from_str(&object, json).unwrap();
+            assert_eq!(ser::to_string(&value, SetOrder::default()).unwrap(), *json);
+        }
+    }
+
+    #[test]
+    fn integer_double_policy() {
+        let type_ = list_type(primitive_type(PrimitiveType::Double));
+        let deserialize = |policy, json: &str| {
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .with_integer_doubles(policy)
+                .deserialize(&mut ::serde_json::Deserializer::from_str(json))
+        };
+        let doubles = |values: &[f64]| {
+            ConjureValue::List(
+                values
+                    .iter()
+                    .map(|v| ConjureValue::Primitive(ConjurePrimitiveValue::double(*v)))
+                    .collect(),
+            )
+        };
+
+        for policy in &[IntegerDoublePolicy::Lenient, IntegerDoublePolicy::Strict] {
+            assert_eq!(deserialize(*policy, "[123]").unwrap(), doubles(&[123.0]));
+        }
+        assert_eq!(
+            deserialize(IntegerDoublePolicy::Lenient, "[9007199254740993]").unwrap(),
+            doubles(&[9007199254740992.0])
+        );
+        assert!(deserialize(IntegerDoublePolicy::Strict, "[9007199254740993]").is_err());
+        // The default is lenient.
+        assert_eq!(
+            from_str(&type_, "[9007199254740993]").unwrap(),
+            doubles(&[9007199254740992.0])
+        );
+    }
+
+    #[test]
+    fn uuid_policy() {
+        let type_ = list_type(primitive_type(PrimitiveType::Uuid));
+        let deserialize = |policy, uuid: &str| {
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .with_uuids(policy)
+                .deserialize(&mut ::serde_json::Deserializer::from_str(&format!(
+                    r#"["{}"]"#,
+                    uuid
+                )))
+        };
+        let canonical = "7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51";
+        let expected = ConjureValue::List(vec![ConjureValue::Primitive(
+            ConjurePrimitiveValue::Uuid(canonical.parse().unwrap()),
+        )]);
+        let uppercase = canonical.to_uppercase();
+        let urn = format!("urn:uuid:{}", canonical);
+
+        for policy in &[UuidPolicy::Lenient, UuidPolicy::Canonical] {
+            assert_eq!(deserialize(*policy, canonical).unwrap(), expected);
+        }
+        assert_eq!(deserialize(UuidPolicy::Lenient, &uppercase).unwrap(), expected);
+        assert_eq!(deserialize(UuidPolicy::Lenient, &urn).unwrap(), expected);
+        assert!(deserialize(UuidPolicy::Canonical, &uppercase).is_err());
+        assert!(deserialize(UuidPolicy::Canonical, &urn).is_err());
+        // The default is lenient.
+        assert_eq!(
+            from_str(&type_, &format!(r#"["{}"]"#, uppercase)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn uuid_policy_applies_to_map_keys() {
+        let type_ = map_type(
+            primitive_type(PrimitiveType::Uuid),
+            primitive_type(PrimitiveType::Integer),
+        );
+        let deserialize = |policy, uuid: &str| {
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .with_uuids(policy)
+                .deserialize(&mut ::serde_json::Deserializer::from_str(&format!(
+                    r#"{{"{}": 1}}"#,
+                    uuid
+                )))
+        };
+        let canonical = "7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51";
+        let expected = ConjureValue::Map(btreemap!(
+            ConjureValue::Primitive(ConjurePrimitiveValue::Uuid(canonical.parse().unwrap())) =>
+                ConjureValue::Primitive(ConjurePrimitiveValue::Integer(1))
+        ));
+        let uppercase = canonical.to_uppercase();
+
+        for policy in &[UuidPolicy::Lenient, UuidPolicy::Canonical] {
+            assert_eq!(deserialize(*policy, canonical).unwrap(), expected);
+        }
+        assert_eq!(deserialize(UuidPolicy::Lenient, &uppercase).unwrap(), expected);
+        assert!(deserialize(UuidPolicy::Canonical, &uppercase).is_err());
+    }
+
+    #[test]
+    fn test_from_slice_matches_from_str() {
+        let type_ = object_definition(
+            "Name",
+            &[
+                field_definition(
+                    "foo",
+                    ResolvedType::List(ListType {
+                        item_type: ResolvedType::Primitive(PrimitiveType::Double).into(),
+                    }),
+                ),
+                field_definition(
+                    "bar",
+                    ResolvedType::Optional(OptionalType {
+                        item_type: ResolvedType::Primitive(PrimitiveType::String).into(),
+                    }),
+                ),
+            ],
+        );
+        let json = r#"{"foo": [1, 2.5, "NaN"], "bar": "héllo"}"#;
+        assert_eq!(
+            from_slice(&type_, json.as_bytes()).unwrap(),
+            from_str(&type_, json).unwrap()
+        );
+        assert!(from_slice(&type_, b"{\"foo\": []} trailing").is_err());
+        assert!(from_slice(&type_, b"{\"foo\": [], \"bar\": \"\xff\"}").is_err());
+    }
+
     #[test]
     fn test_optional() {
         let type_ = ResolvedType::Optional(OptionalType {
@@ -275,6 +569,107 @@ mod test {
         assert!(from_str(&type_, r#"{}"#).is_err());
     }
 
+    #[test]
+    fn partial_objects_leave_out_missing_fields() {
+        let inner = object_definition(
+            "Inner",
+            &[
+                field_definition("required", primitive_type(PrimitiveType::Integer)),
+                field_definition("list", list_type(primitive_type(PrimitiveType::Integer))),
+            ],
+        );
+        let type_ = object_definition(
+            "Outer",
+            &[
+                field_definition("required", primitive_type(PrimitiveType::Integer)),
+                field_definition(
+                    "optional",
+                    optional_type(primitive_type(PrimitiveType::Integer)),
+                ),
+                field_definition("inner", inner),
+            ],
+        );
+        let deserialize = |partial, json: serde_json::Value| {
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .with_partial(partial)
+                .deserialize(&json)
+        };
+        let int = |value| ConjureValue::Primitive(ConjurePrimitiveValue::Integer(value));
+
+        assert_eq!(
+            deserialize(true, json!({ "optional": 1 })).unwrap(),
+            ConjureValue::Object(btreemap!(
+                "optional" => ConjureValue::Optional(Some(int(1).into()))
+            ))
+        );
+        assert!(deserialize(false, json!({ "optional": 1 })).is_err());
+        assert_eq!(
+            deserialize(true, json!({})).unwrap(),
+            ConjureValue::Object(BTreeMap::new())
+        );
+        // Only the outermost object is partial.
+        assert!(deserialize(true, json!({ "inner": { "list": [] } })).is_err());
+        assert_eq!(
+            deserialize(true, json!({ "inner": { "required": 2 } })).unwrap(),
+            ConjureValue::Object(btreemap!(
+                "inner" => ConjureValue::Object(btreemap!(
+                    "required" => int(2),
+                    "list" => ConjureValue::List(vec![])
+                ))
+            ))
+        );
+        // Present fields are still validated.
+        assert!(deserialize(true, json!({ "required": "foo" })).is_err());
+        assert!(deserialize(true, json!({ "unknown": 1 })).is_err());
+    }
+
+    #[test]
+    fn absent_and_null_optionals_are_equal() {
+        let inner = object_definition(
+            "Inner",
+            &[field_definition(
+                "bar",
+                optional_type(primitive_type(PrimitiveType::Double)),
+            )],
+        );
+        let type_ = object_definition(
+            "Outer",
+            &[
+                field_definition("inners", list_type(inner.clone())),
+                field_definition("inner", optional_type(inner)),
+            ],
+        );
+        let absent_bar = || ConjureValue::Object(btreemap!("bar" => ConjureValue::Optional(None)));
+        let expected = ConjureValue::Object(btreemap!(
+            "inners" => ConjureValue::List(vec![absent_bar(), absent_bar()]),
+            "inner" => ConjureValue::Optional(None)
+        ));
+
+        // Missing fields are defaulted to the same value as explicit nulls, whether they come from
+        // JSON text, a JSON value or CBOR.
+        let documents = [
+            json!({ "inners": [{}, {}] }),
+            json!({ "inners": [{}, { "bar": null }], "inner": null }),
+            json!({ "inners": [{ "bar": null }, { "bar": null }] }),
+        ];
+        let mut values = BTreeSet::new();
+        for json in &documents {
+            let cbor = ::serde_cbor::to_vec(json).unwrap();
+            let mut deserializer = ::serde_cbor::Deserializer::from_slice(&cbor);
+            for value in vec![
+                from_str(&type_, &json.to_string()).unwrap(),
+                type_.deserialize(json).unwrap(),
+                type_.deserialize(&mut deserializer).unwrap(),
+            ] {
+                assert_eq!(value, expected, "{}", json);
+                values.insert(value);
+            }
+        }
+        // Ordering agrees with equality, so sets and map keys deduplicate them too.
+        assert_eq!(values.len(), 1);
+    }
+
     #[test]
     fn test_object_collection_fields() {
         let double_type = || ResolvedType::Primitive(PrimitiveType::Double);
@@ -395,4 +790,406 @@ mod test {
         assert!(type_.deserialize(&json!({ "foo": 123 })).is_err());
         assert!(type_.deserialize(&json!({ "bar": 123 })).is_err());
     }
+
+    fn union_of_int_and_string() -> ResolvedType {
+        union_definition(
+            "IntOrString",
+            &[
+                field_definition("int", primitive_type(PrimitiveType::Integer)),
+                field_definition("string", primitive_type(PrimitiveType::String)),
+            ],
+        )
+    }
+
+    fn nested_union() -> ResolvedType {
+        union_definition(
+            "Nested",
+            &[
+                field_definition("inner", union_of_int_and_string()),
+                field_definition("other", primitive_type(PrimitiveType::Boolean)),
+            ],
+        )
+    }
+
+    #[test]
+    fn nested_unions() {
+        let type_ = nested_union();
+        let expected = ConjureValue::Union(ConjureUnionValue {
+            variant: UnionVariant::Known("inner".into()),
+            value: ConjureValue::Union(ConjureUnionValue {
+                variant: UnionVariant::Known("string".into()),
+                value: ConjureValue::Primitive(ConjurePrimitiveValue::String("a".into())).into(),
+            }).into(),
+        });
+
+        let type_first = json!({ "type": "inner", "inner": { "type": "string", "string": "a" } });
+        let type_last = json!({ "inner": { "string": "a", "type": "string" }, "type": "inner" });
+        for json in &[type_first, type_last] {
+            assert_eq!(from_str(&type_, &json.to_string()).unwrap(), expected);
+            assert_eq!(type_.deserialize(json).unwrap(), expected);
+
+            let cbor = ::serde_cbor::to_vec(json).unwrap();
+            let mut deserializer = ::serde_cbor::Deserializer::from_slice(&cbor);
+            assert_eq!(type_.deserialize(&mut deserializer).unwrap(), expected);
+        }
+
+        // The inner union is validated just like a top-level one
+        assert!(
+            type_
+                .deserialize(&json!({ "type": "inner", "inner": { "type": "int", "int": "a" } }))
+                .is_err()
+        );
+        assert!(
+            type_
+                .deserialize(&json!({ "type": "inner", "inner": { "type": "int", "string": 1 } }))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn object_errors_include_field_path() {
+        let inner = object_definition(
+            "Inner",
+            &[field_definition("count", primitive_type(PrimitiveType::Integer))],
+        );
+        let type_ = object_definition("Outer", &[field_definition("items", list_type(inner))]);
+
+        let json = json!({ "items": [{ "count": 1 }, { "count": "two" }] });
+        let error = type_.deserialize(&json).unwrap_err().to_string();
+        assert!(
+            error.starts_with("field `items`: field `count`: invalid type: string \"two\""),
+            "{}",
+            error
+        );
+        let error = from_str(&type_, &json.to_string()).unwrap_err().to_string();
+        assert!(
+            error.starts_with("field `items`: field `count`: invalid type"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn forbid_null_optionals() {
+        let type_ = object_definition(
+            "Name",
+            &[field_definition(
+                "opt",
+                optional_type(primitive_type(PrimitiveType::Integer)),
+            )],
+        );
+        let deserialize = |forbid_null_optionals, json: &serde_json::Value| {
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .with_forbid_null_optionals(forbid_null_optionals)
+                .deserialize(json)
+        };
+        let absent = ConjureValue::Object(btreemap!("opt" => ConjureValue::Optional(None)));
+
+        for forbid_null_optionals in &[false, true] {
+            assert_eq!(deserialize(*forbid_null_optionals, &json!({})).unwrap(), absent);
+            assert_eq!(
+                deserialize(*forbid_null_optionals, &json!({ "opt": 1 })).unwrap(),
+                ConjureValue::Object(btreemap!("opt" => ConjureValue::Optional(Some(
+                    ConjureValue::Primitive(ConjurePrimitiveValue::Integer(1)).into()
+                ))))
+            );
+        }
+        assert_eq!(deserialize(false, &json!({ "opt": null })).unwrap(), absent);
+        assert!(deserialize(true, &json!({ "opt": null })).is_err());
+        // The setting applies to nested objects too.
+        let list = list_type(type_.clone());
+        assert!(
+            list.with_profile(StrictnessProfile::Server)
+                .with_forbid_null_optionals(true)
+                .deserialize(&json!([{ "opt": null }]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn required_collections_reject_null() {
+        let string = || primitive_type(PrimitiveType::String);
+        for (name, collection_type, empty) in vec![
+            (
+                "map",
+                map_type(string(), string()),
+                ConjureValue::Map(BTreeMap::new()),
+            ),
+            ("list", list_type(string()), ConjureValue::List(vec![])),
+            ("set", set_type(string()), ConjureValue::Set(BTreeSet::new())),
+        ] {
+            let type_ = object_definition("Name", &[field_definition("m", collection_type)]);
+            let expected = ConjureValue::Object(btreemap!("m" => empty));
+            let explicit_empty = if name == "map" {
+                json!({ "m": {} })
+            } else {
+                json!({ "m": [] })
+            };
+            for json in &[explicit_empty, json!({})] {
+                assert_eq!(from_str(&type_, &json.to_string()).unwrap(), expected);
+                assert_eq!(type_.deserialize(json).unwrap(), expected);
+                let cbor = ::serde_cbor::to_vec(json).unwrap();
+                let mut deserializer = ::serde_cbor::Deserializer::from_slice(&cbor);
+                assert_eq!(type_.deserialize(&mut deserializer).unwrap(), expected);
+            }
+
+            let null = json!({ "m": null });
+            assert!(from_str(&type_, &null.to_string()).is_err(), "{}", name);
+            assert!(type_.deserialize(&null).is_err(), "{}", name);
+            let cbor = ::serde_cbor::to_vec(&null).unwrap();
+            let mut deserializer = ::serde_cbor::Deserializer::from_slice(&cbor);
+            assert!(type_.deserialize(&mut deserializer).is_err(), "{}", name);
+            // Some formats represent null as an absent option rather than as a unit.
+            let value = ::serde_value::Value::Map(btreemap!(
+                ::serde_value::Value::String("m".into()) => ::serde_value::Value::Option(None)
+            ));
+            assert!(type_.deserialize(value).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn required_lists_in_cbor() {
+        let type_ = object_definition(
+            "Name",
+            &[field_definition(
+                "m",
+                list_type(primitive_type(PrimitiveType::String)),
+            )],
+        );
+        let empty = ConjureValue::Object(btreemap!("m" => ConjureValue::List(vec![])));
+        let deserialize = |cbor: &[u8]| {
+            let mut deserializer = ::serde_cbor::Deserializer::from_slice(cbor);
+            type_.deserialize(&mut deserializer)
+        };
+
+        // `{}`, as a definite and an indefinite length map.
+        assert_eq!(deserialize(b"\xa0").unwrap(), empty);
+        assert_eq!(deserialize(b"\xbf\xff").unwrap(), empty);
+        // `{"m": []}`, as a definite and an indefinite length array.
+        assert_eq!(deserialize(b"\xa1\x61m\x80").unwrap(), empty);
+        assert_eq!(deserialize(b"\xa1\x61m\x9f\xff").unwrap(), empty);
+        // `{"m": null}`, and CBOR's `undefined`, which has no JSON equivalent.
+        assert!(deserialize(b"\xa1\x61m\xf6").is_err());
+        assert!(deserialize(b"\xa1\x61m\xf7").is_err());
+    }
+
+    #[test]
+    fn list_of_unions_is_order_sensitive() {
+        let type_ = list_type(union_of_int_and_string());
+
+        let forwards = from_str(
+            &type_,
+            r#"[{"type": "int", "int": 1}, {"type": "string", "string": "a"}]"#,
+        ).unwrap();
+        let backwards = from_str(
+            &type_,
+            r#"[{"type": "string", "string": "a"}, {"type": "int", "int": 1}]"#,
+        ).unwrap();
+
+        assert_ne!(forwards, backwards);
+        assert_eq!(
+            forwards,
+            from_str(
+                &type_,
+                r#"[{"int": 1, "type": "int"}, {"string": "a", "type": "string"}]"#,
+            ).unwrap()
+        );
+    }
+
+    #[test]
+    fn set_of_unions_is_order_insensitive() {
+        let type_ = set_type(union_of_int_and_string());
+
+        let forwards = from_str(
+            &type_,
+            r#"[{"type": "int", "int": 1}, {"type": "string", "string": "a"}]"#,
+        ).unwrap();
+        let backwards = from_str(
+            &type_,
+            r#"[{"type": "string", "string": "a"}, {"type": "int", "int": 1}]"#,
+        ).unwrap();
+
+        assert_eq!(forwards, backwards);
+
+        // Sets of unions still reject duplicates, regardless of the order of the union's keys
+        assert!(
+            from_str(
+                &type_,
+                r#"[{"type": "int", "int": 1}, {"int": 1, "type": "int"}]"#,
+            ).is_err()
+        );
+    }
+
+    #[test]
+    fn profile_determines_unknown_field_handling() {
+        let type_ = object_definition(
+            "Name",
+            &[field_definition("foo", primitive_type(PrimitiveType::Integer))],
+        );
+        let json = json!({ "foo": 1, "whoami": { "nested": [1, 2] } });
+
+        assert!(
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .deserialize(&json)
+                .is_err()
+        );
+        assert_eq!(
+            type_
+                .with_profile(StrictnessProfile::Client)
+                .deserialize(&json)
+                .unwrap(),
+            ConjureValue::Object(btreemap!(
+                "foo" => ConjureValue::Primitive(ConjurePrimitiveValue::Integer(1))
+            ))
+        );
+
+        // Duplicate fields are rejected regardless of the profile
+        let duplicated = r#"{"foo": 1, "foo": 2}"#;
+        assert!(from_str(type_.with_profile(StrictnessProfile::Client), duplicated).is_err());
+    }
+
+    #[test]
+    fn profile_determines_set_duplicate_handling() {
+        let type_ = set_type(primitive_type(PrimitiveType::Integer));
+        let json = json!([1, 1, 2]);
+
+        assert!(
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .deserialize(&json)
+                .is_err()
+        );
+        assert_eq!(
+            type_
+                .with_profile(StrictnessProfile::Client)
+                .deserialize(&json)
+                .unwrap(),
+            from_str(&type_, "[1, 2]").unwrap()
+        );
+    }
+
+    #[test]
+    fn profile_determines_null_any_handling() {
+        // The profile also applies to nested types
+        let type_ = list_type(primitive_type(PrimitiveType::Any));
+        let json = json!([1, null]);
+
+        assert!(
+            type_
+                .with_profile(StrictnessProfile::Server)
+                .deserialize(&json)
+                .is_err()
+        );
+        assert!(
+            type_
+                .with_profile(StrictnessProfile::Client)
+                .deserialize(&json)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn both_profiles_accept_unknown_enum_values() {
+        let type_ = enum_definition("Enum", &["ONE"]);
+        for profile in &[StrictnessProfile::Client, StrictnessProfile::Server] {
+            assert_eq!(
+                type_.with_profile(*profile).deserialize(&json!("TWO")).unwrap(),
+                ConjureValue::Enum(EnumValue::Unknown("TWO".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn strict_enum_casing() {
+        let type_ = enum_definition("Fruit", &["APPLE"]);
+        let deserialize = |strict_enum_casing, json: &serde_json::Value| {
+            type_
+                .with_profile(StrictnessProfile::Client)
+                .with_strict_enum_casing(strict_enum_casing)
+                .deserialize(json)
+        };
+
+        for strict_enum_casing in &[false, true] {
+            assert_eq!(
+                deserialize(*strict_enum_casing, &json!("APPLE")).unwrap(),
+                ConjureValue::Enum(EnumValue::Known("APPLE".to_string()))
+            );
+            assert_eq!(
+                deserialize(*strict_enum_casing, &json!("BANANA")).unwrap(),
+                ConjureValue::Enum(EnumValue::Unknown("BANANA".to_string()))
+            );
+        }
+        assert_eq!(
+            deserialize(false, &json!("apple")).unwrap(),
+            ConjureValue::Enum(EnumValue::Unknown("apple".to_string()))
+        );
+        let error = deserialize(true, &json!("apple")).unwrap_err().to_string();
+        assert_eq!(error, "enum value `apple` does not match the casing of `APPLE`");
+    }
+
+    #[test]
+    fn uuid_rid_and_bearertoken_map_keys() {
+        let map_of = |key_type| {
+            map_type(
+                primitive_type(key_type),
+                primitive_type(PrimitiveType::Integer),
+            )
+        };
+        let entry = |key, value| {
+            (
+                ConjureValue::Primitive(key),
+                ConjureValue::Primitive(ConjurePrimitiveValue::Integer(value)),
+            )
+        };
+
+        let uuid = "7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51";
+        let type_ = map_of(PrimitiveType::Uuid);
+        assert_eq!(
+            from_str(&type_, &format!(r#"{{"{}": 1}}"#, uuid)).unwrap(),
+            ConjureValue::Map(
+                vec![entry(ConjurePrimitiveValue::Uuid(uuid.parse().unwrap()), 1)]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert!(from_str(&type_, r#"{"not-a-uuid": 1}"#).is_err());
+        // Different spellings of the same uuid are the same key.
+        let duplicated = format!(r#"{{"{}": 1, "{}": 2}}"#, uuid, uuid.to_uppercase());
+        assert!(from_str(&type_, &duplicated).is_err());
+
+        let rid = "ri.service.instance.type.locator";
+        let type_ = map_of(PrimitiveType::Rid);
+        assert_eq!(
+            from_str(&type_, &format!(r#"{{"{}": 1}}"#, rid)).unwrap(),
+            ConjureValue::Map(
+                vec![entry(ConjurePrimitiveValue::Rid(rid.to_string()), 1)]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        let duplicated = format!(r#"{{"{}": 1, "{}": 2}}"#, rid, rid);
+        assert!(from_str(&type_, &duplicated).is_err());
+
+        let type_ = map_of(PrimitiveType::Bearertoken);
+        assert_eq!(
+            from_str(&type_, r#"{"token": 1}"#).unwrap(),
+            ConjureValue::Map(
+                vec![entry(ConjurePrimitiveValue::Bearertoken("token".to_string()), 1)]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert!(from_str(&type_, r#"{"token": 1, "token": 2}"#).is_err());
+    }
+
+    #[test]
+    fn list_and_set_with_same_elements_are_not_equal() {
+        let json = r#"[{"type": "int", "int": 1}, {"type": "string", "string": "a"}]"#;
+        let list = from_str(&list_type(union_of_int_and_string()), json).unwrap();
+        let set = from_str(&set_type(union_of_int_and_string()), json).unwrap();
+
+        assert_ne!(list, set);
+    }
 }
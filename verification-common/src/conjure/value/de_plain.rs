@@ -17,6 +17,7 @@
 //! [PLAIN format]: https://github.com/palantir/conjure/blob/develop/docs/spec/wire.md#plain-format
 
 use conjure::ir::PrimitiveType;
+use conjure::resolved_type::OptionalType;
 use conjure::resolved_type::ResolvedType;
 use conjure::value::double::ConjureDouble;
 use conjure::value::ConjurePrimitiveValue;
@@ -39,6 +40,18 @@ pub fn deserialize_plain(
             let de = serde_plain::Deserializer::from_str(&str);
             Ok(ConjureValue::Enum(enum_def.deserialize(de)?))
         }
+        // serde_plain can't deserialize doubles either, so parse them ourselves, treating an empty
+        // string as absent the way serde_plain does.
+        ResolvedType::Optional(OptionalType { ref item_type })
+            if **item_type == ResolvedType::Primitive(PrimitiveType::Double) =>
+        {
+            if str.is_empty() {
+                return Ok(ConjureValue::Optional(None));
+            }
+            Ok(ConjureValue::Optional(Some(Box::new(ConjureValue::Primitive(
+                deserialize_plain_primitive(&PrimitiveType::Double, str)?,
+            )))))
+        }
         // TODO(dsanduleac): should verify thing inside optional is also plain-serializable
         // Otherwise, it will still fail but with a less explicit error inside serde_plain
         ResolvedType::Optional(_) => {
@@ -115,6 +128,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_deserialize_optional_double() {
+        let type_ = optional_type(primitive_type(PrimitiveType::Double));
+        let double = |v: f64| {
+            ConjureValue::Optional(Some(
+                ConjureValue::Primitive(ConjurePrimitiveValue::double(v)).into(),
+            ))
+        };
+        assert_eq!(deserialize_plain(&type_, "1.5").unwrap(), double(1.5));
+        assert_eq!(deserialize_plain(&type_, "NaN").unwrap(), double(::std::f64::NAN));
+        assert_eq!(
+            deserialize_plain(&type_, "Infinity").unwrap(),
+            double(::std::f64::INFINITY)
+        );
+        assert_eq!(
+            deserialize_plain(&type_, "-Infinity").unwrap(),
+            double(::std::f64::NEG_INFINITY)
+        );
+        assert_eq!(deserialize_plain(&type_, "").unwrap(), ConjureValue::Optional(None));
+        deserialize_plain(&type_, "foo").expect_err("Should not deserialize foo as a double");
+    }
+
     #[test]
     fn test_deserialize_any() {
         deserialize_plain(&primitive_type(PrimitiveType::Any), "123")
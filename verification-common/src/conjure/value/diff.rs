@@ -0,0 +1,203 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Locates where two `ConjureValue`s differ, so mismatches can be reported by path rather than by
+//! dumping both values.
+
+use super::ser::{self, SetOrder};
+use super::*;
+
+/// The path of the first place where `left` and `right` differ, or `None` if they're equal.
+///
+/// Paths start at `$` and descend into object fields as `.name`, union values as `.variant`, list
+/// and set elements as `[index]` and map values as `[key]`, with keys rendered as JSON. Set
+/// elements are indexed in their canonical order, and object fields and map entries are compared
+/// in key order. Optionals that are both present are descended into without adding to the path.
+pub fn first_difference(left: &ConjureValue, right: &ConjureValue) -> Option<String> {
+    let mut path = "$".to_string();
+    if differ(left, right, &mut path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Whether the values differ, extending `path` to the first difference if so.
+fn differ(left: &ConjureValue, right: &ConjureValue, path: &mut String) -> bool {
+    if left == right {
+        return false;
+    }
+    match (left, right) {
+        (ConjureValue::Optional(Some(left)), ConjureValue::Optional(Some(right))) => {
+            differ(left, right, path);
+        }
+        (ConjureValue::Object(left), ConjureValue::Object(right)) => {
+            let name = left
+                .keys()
+                .chain(right.keys())
+                .filter(|name| left.get(*name) != right.get(*name))
+                .min();
+            if let Some(name) = name {
+                path.push('.');
+                path.push_str(name);
+                if let (Some(left), Some(right)) = (left.get(name), right.get(name)) {
+                    differ(left, right, path);
+                }
+            }
+        }
+        (ConjureValue::Union(left), ConjureValue::Union(right))
+            if left.variant == right.variant =>
+        {
+            let name = match left.variant {
+                UnionVariant::Known(ref name) | UnionVariant::Unknown(ref name) => name,
+            };
+            path.push('.');
+            path.push_str(name);
+            differ(&left.value, &right.value, path);
+        }
+        (ConjureValue::List(left), ConjureValue::List(right)) => {
+            differ_elements(left.iter(), right.iter(), path);
+        }
+        (ConjureValue::Set(left), ConjureValue::Set(right)) => {
+            differ_elements(left.iter(), right.iter(), path);
+        }
+        (ConjureValue::Map(left), ConjureValue::Map(right)) => {
+            let key = left
+                .keys()
+                .chain(right.keys())
+                .filter(|key| left.get(*key) != right.get(*key))
+                .min();
+            if let Some(key) = key {
+                let rendered = ser::to_string(key, SetOrder::Canonical)
+                    .unwrap_or_else(|_| format!("{:?}", key));
+                path.push_str(&format!("[{}]", rendered));
+                if let (Some(left), Some(right)) = (left.get(key), right.get(key)) {
+                    differ(left, right, path);
+                }
+            }
+        }
+        _ => {}
+    }
+    true
+}
+
+/// Extends `path` to the first index where the sequences differ, if any.
+fn differ_elements<'a, L, R>(mut left: L, mut right: R, path: &mut String)
+where
+    L: Iterator<Item = &'a ConjureValue>,
+    R: Iterator<Item = &'a ConjureValue>,
+{
+    let mut index = 0;
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return,
+            (Some(left), Some(right)) if left == right => index += 1,
+            (left, right) => {
+                path.push_str(&format!("[{}]", index));
+                if let (Some(left), Some(right)) = (left, right) {
+                    differ(left, right, path);
+                }
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn int(value: i32) -> ConjureValue {
+        ConjureValue::Primitive(ConjurePrimitiveValue::Integer(value))
+    }
+
+    fn string(value: &str) -> ConjureValue {
+        ConjureValue::Primitive(ConjurePrimitiveValue::String(value.to_string()))
+    }
+
+    fn object(fields: Vec<(&str, ConjureValue)>) -> ConjureValue {
+        ConjureValue::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn equal_values_have_no_difference() {
+        let value = object(vec![("foo", ConjureValue::List(vec![int(1), int(2)]))]);
+        assert_eq!(first_difference(&value, &value), None);
+    }
+
+    #[test]
+    fn paths_to_differences() {
+        let nested = |item| {
+            object(vec![
+                ("a", int(1)),
+                (
+                    "b",
+                    ConjureValue::Optional(Some(
+                        ConjureValue::List(vec![int(1), object(vec![("c", item)])]).into(),
+                    )),
+                ),
+            ])
+        };
+        assert_eq!(
+            first_difference(&nested(int(2)), &nested(int(3))),
+            Some("$.b[1].c".to_string())
+        );
+
+        let list = |items| ConjureValue::List(items);
+        assert_eq!(
+            first_difference(&list(vec![int(1)]), &list(vec![int(1), int(2)])),
+            Some("$[1]".to_string())
+        );
+
+        let set = |items: Vec<ConjureValue>| ConjureValue::Set(items.into_iter().collect());
+        assert_eq!(
+            first_difference(&set(vec![int(1), int(3)]), &set(vec![int(1), int(2)])),
+            Some("$[1]".to_string())
+        );
+
+        let map = |value: ConjureValue| ConjureValue::Map(btreemap!(string("key") => value));
+        assert_eq!(
+            first_difference(&map(int(1)), &map(int(2))),
+            Some("$[\"key\"]".to_string())
+        );
+
+        let union = |variant: &str, value| {
+            ConjureValue::Union(ConjureUnionValue::new(
+                UnionVariant::Known(variant.to_string()),
+                Box::new(value),
+            ))
+        };
+        assert_eq!(
+            first_difference(&union("foo", int(1)), &union("foo", int(2))),
+            Some("$.foo".to_string())
+        );
+        assert_eq!(
+            first_difference(&union("foo", int(1)), &union("bar", int(1))),
+            Some("$".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_fields_differ_at_the_field() {
+        let left = object(vec![("a", int(1)), ("b", int(2))]);
+        let right = object(vec![("a", int(1))]);
+        assert_eq!(first_difference(&left, &right), Some("$.b".to_string()));
+        assert_eq!(first_difference(&right, &left), Some("$.b".to_string()));
+    }
+}
@@ -15,18 +15,24 @@
 //! ConjureDouble is a wrapper around f64 that assigns deterministic Eq and Ord implementations to
 //! NaN and +/- INFINITY.
 
+use serde::de::DeserializeSeed;
 use serde::de::Error;
 use serde::de::IntoDeserializer;
 use serde::de::Visitor;
 use serde::Deserialize;
 use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 use std::cmp::Ordering;
 use std::fmt::{self, Display};
 use std::num::ParseFloatError;
 use std::str::FromStr;
 
 /// Represents a finite `f64` or NaN / NegativeInfinity / PositiveInfinity.
-#[derive(Serialize, Debug, PartialEq, PartialOrd, Eq, Ord)]
+///
+/// Serializes to the Conjure wire format: finite values are numbers, whereas the special values
+/// are the strings `"NaN"`, `"Infinity"` and `"-Infinity"`.
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub enum ConjureDouble {
     NegativeInfinity,
     Finite(FiniteDouble),
@@ -41,8 +47,7 @@ pub enum ConjureDouble {
 pub struct FiniteDouble(f64);
 
 impl FiniteDouble {
-    #[allow(dead_code)]
-    fn value(&self) -> f64 {
+    pub fn value(&self) -> f64 {
         self.0
     }
 }
@@ -85,71 +90,158 @@ impl Ord for FiniteDouble {
     }
 }
 
+// Serialization
+
+impl Serialize for ConjureDouble {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            ConjureDouble::Finite(ref fd) => serializer.serialize_f64(fd.0),
+            // JSON has no representation for these, so they are sent as strings.
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
 // Deserialization
 
+/// What to do with an integer token that can't be represented exactly as an `f64`, such as
+/// `9007199254740993` (2^53 + 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegerDoublePolicy {
+    /// Round to the nearest `f64`, logging a warning about the lost precision.
+    Lenient,
+    /// Fail to deserialize.
+    Strict,
+}
+
+impl Default for IntegerDoublePolicy {
+    fn default() -> IntegerDoublePolicy {
+        IntegerDoublePolicy::Lenient
+    }
+}
+
+impl FromStr for IntegerDoublePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<IntegerDoublePolicy, String> {
+        match s {
+            "lenient" => Ok(IntegerDoublePolicy::Lenient),
+            "strict" => Ok(IntegerDoublePolicy::Strict),
+            other => Err(format!(
+                "Unknown integer double policy '{}', expected 'lenient' or 'strict'",
+                other
+            )),
+        }
+    }
+}
+
+/// Deserializes with the default (lenient) [IntegerDoublePolicy].
+///
+/// [IntegerDoublePolicy]: enum.IntegerDoublePolicy.html
 impl<'de> Deserialize<'de> for ConjureDouble {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        #[derive(Deserialize)]
-        enum LiteralValue {
-            NaN,
-            #[serde(rename = "Infinity")]
-            PositiveInfinity,
-            #[serde(rename = "-Infinity")]
-            NegativeInfinity,
-        }
+        IntegerDoublePolicy::default().deserialize(deserializer)
+    }
+}
 
-        struct ConjureDoubleVisitor;
+/// Deserializes a `ConjureDouble`, handling imprecise integer tokens according to the policy.
+impl<'de> DeserializeSeed<'de> for IntegerDoublePolicy {
+    type Value = ConjureDouble;
 
-        impl<'de> Visitor<'de> for ConjureDoubleVisitor {
-            type Value = ConjureDouble;
+    fn deserialize<D>(self, deserializer: D) -> Result<ConjureDouble, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ConjureDoubleVisitor(self))
+    }
+}
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str(
-                    "a float or an string value of NaN, NegativeInfinity, \
-                     or PositiveInfinity",
-                )
-            }
+#[derive(Deserialize)]
+enum LiteralValue {
+    NaN,
+    #[serde(rename = "Infinity")]
+    PositiveInfinity,
+    #[serde(rename = "-Infinity")]
+    NegativeInfinity,
+}
 
-            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
-            where
-                E: Error,
-            {
-                self.visit_f64(v as f64)
-            }
+struct ConjureDoubleVisitor(IntegerDoublePolicy);
 
-            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
-            where
-                E: Error,
-            {
-                self.visit_f64(v as f64)
+impl ConjureDoubleVisitor {
+    fn visit_integer<T, E>(self, v: T, value: f64, exact: bool) -> Result<ConjureDouble, E>
+    where
+        T: Display,
+        E: Error,
+    {
+        if !exact {
+            match self.0 {
+                IntegerDoublePolicy::Lenient => warn!(
+                    "integer {} is not exactly representable as a double, using {}",
+                    v, value
+                ),
+                IntegerDoublePolicy::Strict => {
+                    return Err(E::custom(format_args!(
+                        "integer {} is not exactly representable as a double",
+                        v
+                    )))
+                }
             }
+        }
+        Ok(ConjureDouble::new(value))
+    }
+}
 
-            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
-            where
-                E: Error,
-            {
-                // Relying on ::new to ensure that if this value passed in by the deserializer is somehow
-                // not finite, it ends up being deserialized to the correct variant.
-                Ok(ConjureDouble::new(v))
-            }
+impl<'de> Visitor<'de> for ConjureDoubleVisitor {
+    type Value = ConjureDouble;
 
-            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
-            where
-                E: Error,
-            {
-                let literal = LiteralValue::deserialize(v.into_deserializer())?;
-                Ok(match literal {
-                    LiteralValue::NaN => ConjureDouble::NaN,
-                    LiteralValue::NegativeInfinity => ConjureDouble::NegativeInfinity,
-                    LiteralValue::PositiveInfinity => ConjureDouble::PositiveInfinity,
-                })
-            }
-        }
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(
+            "a float or an string value of NaN, NegativeInfinity, \
+             or PositiveInfinity",
+        )
+    }
 
-        deserializer.deserialize_any(ConjureDoubleVisitor)
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let value = v as f64;
+        self.visit_integer(v, value, value as i128 == i128::from(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let value = v as f64;
+        self.visit_integer(v, value, value as u128 == u128::from(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        // Relying on ::new to ensure that if this value passed in by the deserializer is somehow
+        // not finite, it ends up being deserialized to the correct variant.
+        Ok(ConjureDouble::new(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let literal = LiteralValue::deserialize(v.into_deserializer())?;
+        Ok(match literal {
+            LiteralValue::NaN => ConjureDouble::NaN,
+            LiteralValue::NegativeInfinity => ConjureDouble::NegativeInfinity,
+            LiteralValue::PositiveInfinity => ConjureDouble::PositiveInfinity,
+        })
     }
 }
 
@@ -187,6 +279,19 @@ mod test {
         assert_eq!(des, ConjureDouble::Finite(FiniteDouble(-0.0)));
     }
 
+    #[test]
+    fn ser_json() {
+        let ser = |d| ::serde_json::to_string(&ConjureDouble::new(d)).unwrap();
+        assert_eq!(ser(::std::f64::NAN), r#""NaN""#);
+        assert_eq!(ser(::std::f64::INFINITY), r#""Infinity""#);
+        assert_eq!(ser(::std::f64::NEG_INFINITY), r#""-Infinity""#);
+        assert_eq!(ser(-1.5), "-1.5");
+
+        let round_tripped: ConjureDouble =
+            ::serde_json::from_str(&ser(::std::f64::INFINITY)).unwrap();
+        assert_eq!(round_tripped, ConjureDouble::PositiveInfinity);
+    }
+
     #[test]
     fn deser_from_str() {
         let des: ConjureDouble = "NaN".parse().unwrap();
@@ -201,4 +306,28 @@ mod test {
         let des: ConjureDouble = "-0".parse().unwrap();
         assert_eq!(des, ConjureDouble::Finite(FiniteDouble(-0.0)));
     }
+
+    #[test]
+    fn deser_integers() {
+        let deser = |policy: IntegerDoublePolicy, json: &str| {
+            policy.deserialize(&mut ::serde_json::Deserializer::from_str(json))
+        };
+        for policy in &[IntegerDoublePolicy::Lenient, IntegerDoublePolicy::Strict] {
+            assert_eq!(deser(*policy, "123").unwrap(), ConjureDouble::new(123.0));
+            assert_eq!(deser(*policy, "-123").unwrap(), ConjureDouble::new(-123.0));
+            assert_eq!(
+                deser(*policy, "9007199254740992").unwrap(),
+                ConjureDouble::new(9007199254740992.0)
+            );
+        }
+
+        // 2^53 + 1 is the smallest positive integer that isn't exactly representable.
+        assert_eq!(
+            deser(IntegerDoublePolicy::Lenient, "9007199254740993").unwrap(),
+            ConjureDouble::new(9007199254740992.0)
+        );
+        assert!(deser(IntegerDoublePolicy::Strict, "9007199254740993").is_err());
+        assert!(deser(IntegerDoublePolicy::Strict, "-9007199254740993").is_err());
+        assert!(deser(IntegerDoublePolicy::Strict, "9223372036854775807").is_err());
+    }
 }
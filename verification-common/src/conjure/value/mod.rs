@@ -28,9 +28,13 @@ use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use uuid::Uuid;
 
+pub mod canonical_uuid;
 pub mod de;
 pub mod de_plain;
+pub mod diff;
 pub mod double;
+pub mod profile;
+pub mod ser;
 mod visitors;
 
 #[derive(ConjureSerialize, Debug, PartialEq, PartialOrd, Eq, Ord)]
@@ -62,6 +66,8 @@ impl ConjurePrimitiveValue {
 pub enum ConjureValue {
     Primitive(ConjurePrimitiveValue),
     // complex
+    /// Absent optional object fields are deserialized as `Optional(None)` too, so they compare
+    /// equal to explicit nulls.
     Optional(Option<Box<ConjureValue>>),
     Object(BTreeMap<String, ConjureValue>),
     Enum(EnumValue),
@@ -0,0 +1,72 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! How strictly Conjure JSON is deserialized, depending on which side of the wire is reading it.
+
+use std::str::FromStr;
+
+/// Bundles the deserialization knobs that differ between Conjure clients and servers.
+///
+/// Conjure servers are strict about the requests they accept, whereas Conjure clients are lenient
+/// about the responses they read so that servers can evolve their APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrictnessProfile {
+    /// How a Conjure client reads a response. Used when verifying servers.
+    Client,
+    /// How a Conjure server reads a request. Used when verifying clients.
+    Server,
+}
+
+impl Default for StrictnessProfile {
+    fn default() -> StrictnessProfile {
+        StrictnessProfile::Server
+    }
+}
+
+impl StrictnessProfile {
+    /// Whether object fields that aren't in the type definition are ignored rather than rejected.
+    pub fn skip_unknown_fields(self) -> bool {
+        self == StrictnessProfile::Client
+    }
+
+    /// Whether a set containing the same element twice is rejected rather than deduplicated.
+    pub fn fail_on_set_duplicates(self) -> bool {
+        self == StrictnessProfile::Server
+    }
+
+    /// Whether `null` is accepted as a value of type `any`.
+    pub fn allow_null_any(self) -> bool {
+        self == StrictnessProfile::Client
+    }
+
+    /// Whether a UTF-8 byte order mark before a JSON document is skipped rather than rejected.
+    pub fn skip_json_bom(self) -> bool {
+        self == StrictnessProfile::Client
+    }
+}
+
+impl FromStr for StrictnessProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StrictnessProfile, String> {
+        match s {
+            "client" => Ok(StrictnessProfile::Client),
+            "server" => Ok(StrictnessProfile::Server),
+            other => Err(format!(
+                "Unknown strictness profile '{}', expected 'client' or 'server'",
+                other
+            )),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Renders a conjure::value::ConjureValue back into the Conjure JSON wire format.
+//!
+//! The output is deterministic: object fields and map entries are emitted in key order, and set
+//! elements are emitted in the order given by [SetOrder].
+//!
+//! [SetOrder]: enum.SetOrder.html
+
+use super::*;
+use serde::ser::Error;
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde::Serializer;
+use serde_json;
+
+/// The order in which the elements of a set are emitted.
+///
+/// Both orders depend only on the elements themselves, so they are stable across runs and
+/// platforms regardless of the order in which the elements were received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOrder {
+    /// The order of `ConjureValue`'s `Ord` implementation, which is how sets are stored.
+    ///
+    /// Primitives of different types are ordered by type: string, integer, double, boolean,
+    /// safelong, binary, uuid, rid, bearertoken, datetime, any. Primitives of the same type are
+    /// ordered by value, with doubles ordered as `-Infinity` < finite < `Infinity` < `NaN`.
+    Canonical,
+    /// Byte-wise order of each element's rendered JSON, which is easiest to scan in a diff.
+    JsonText,
+}
+
+impl Default for SetOrder {
+    fn default() -> SetOrder {
+        SetOrder::Canonical
+    }
+}
+
+/// A `Serialize` view of a `ConjureValue` as Conjure JSON.
+#[derive(Debug, Clone, Copy)]
+pub struct ConjureJson<'a> {
+    value: &'a ConjureValue,
+    set_order: SetOrder,
+}
+
+impl<'a> ConjureJson<'a> {
+    pub fn new(value: &'a ConjureValue) -> ConjureJson<'a> {
+        ConjureJson::with_set_order(value, SetOrder::default())
+    }
+
+    pub fn with_set_order(value: &'a ConjureValue, set_order: SetOrder) -> ConjureJson<'a> {
+        ConjureJson { value, set_order }
+    }
+
+    fn child<'b>(&self, value: &'b ConjureValue) -> ConjureJson<'b> {
+        ConjureJson::with_set_order(value, self.set_order)
+    }
+}
+
+/// Renders `value` as a compact Conjure JSON string.
+pub fn to_string(value: &ConjureValue, set_order: SetOrder) -> serde_json::Result<String> {
+    serde_json::to_string(&ConjureJson::with_set_order(value, set_order))
+}
+
+/// Renders `value` for display in an error: canonical Conjure JSON, truncated to roughly
+/// `max_len` bytes.
+///
+/// Falls back to the `Debug` representation if the value can't be represented as JSON.
+pub fn to_display_string(value: &ConjureValue, max_len: usize) -> String {
+    let mut rendered =
+        to_string(value, SetOrder::Canonical).unwrap_or_else(|_| format!("{:?}", value));
+    if rendered.len() > max_len {
+        let mut end = max_len;
+        while !rendered.is_char_boundary(end) {
+            end -= 1;
+        }
+        rendered.truncate(end);
+        rendered.push_str("...<truncated>");
+    }
+    rendered
+}
+
+impl<'a> Serialize for ConjureJson<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.value {
+            ConjureValue::Primitive(primitive) => serialize_primitive(primitive, serializer),
+            ConjureValue::Optional(None) => serializer.serialize_none(),
+            ConjureValue::Optional(Some(value)) => serializer.serialize_some(&self.child(value)),
+            ConjureValue::Object(fields) => {
+                // Absent optional fields are omitted rather than serialized as null.
+                let mut map = serializer.serialize_map(None)?;
+                for (name, value) in fields {
+                    if *value != ConjureValue::Optional(None) {
+                        map.serialize_entry(name, &self.child(value))?;
+                    }
+                }
+                map.end()
+            }
+            ConjureValue::Enum(EnumValue::Known(value))
+            | ConjureValue::Enum(EnumValue::Unknown(value)) => serializer.serialize_str(value),
+            ConjureValue::Union(ConjureUnionValue { variant, value }) => {
+                let name = match variant {
+                    UnionVariant::Known(name) | UnionVariant::Unknown(name) => name,
+                };
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("type", name)?;
+                map.serialize_entry(name, &self.child(value))?;
+                map.end()
+            }
+            ConjureValue::List(items) => {
+                serializer.collect_seq(items.iter().map(|item| self.child(item)))
+            }
+            ConjureValue::Set(items) => match self.set_order {
+                SetOrder::Canonical => {
+                    serializer.collect_seq(items.iter().map(|item| self.child(item)))
+                }
+                SetOrder::JsonText => {
+                    let mut rendered = items
+                        .iter()
+                        .map(|item| {
+                            serde_json::to_string(&self.child(item)).map(|text| (text, item))
+                        }).collect::<Result<Vec<_>, _>>()
+                        .map_err(S::Error::custom)?;
+                    rendered.sort_by(|a, b| a.0.cmp(&b.0));
+                    serializer.collect_seq(rendered.into_iter().map(|(_, item)| self.child(item)))
+                }
+            },
+            ConjureValue::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(&map_key(key)?, &self.child(value))?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+fn serialize_primitive<S>(
+    primitive: &ConjurePrimitiveValue,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match primitive {
+        ConjurePrimitiveValue::Integer(value) => serializer.serialize_i32(*value),
+        ConjurePrimitiveValue::Safelong(value) => serializer.serialize_i64(*value),
+        ConjurePrimitiveValue::Boolean(value) => serializer.serialize_bool(*value),
+        ConjurePrimitiveValue::Double(value) => value.serialize(serializer),
+        ConjurePrimitiveValue::Any(value) => value.serialize(serializer),
+        other => serializer.serialize_str(&primitive_string(other)),
+    }
+}
+
+/// Renders a primitive the way it appears inside a JSON string, e.g. as a map key.
+fn primitive_string(primitive: &ConjurePrimitiveValue) -> String {
+    match primitive {
+        ConjurePrimitiveValue::String(value)
+        | ConjurePrimitiveValue::Rid(value)
+        | ConjurePrimitiveValue::Bearertoken(value) => value.clone(),
+        ConjurePrimitiveValue::Integer(value) => value.to_string(),
+        ConjurePrimitiveValue::Double(value) => value.to_string(),
+        ConjurePrimitiveValue::Boolean(value) => value.to_string(),
+        ConjurePrimitiveValue::Safelong(value) => value.to_string(),
+        ConjurePrimitiveValue::Binary(value) => ::base64::encode(&value.0),
+        ConjurePrimitiveValue::Uuid(value) => value.to_string(),
+        ConjurePrimitiveValue::Datetime(value) => value.to_rfc3339(),
+        ConjurePrimitiveValue::Any(value) => format!("{:?}", value),
+    }
+}
+
+fn map_key<E>(key: &ConjureValue) -> Result<String, E>
+where
+    E: Error,
+{
+    match key {
+        ConjureValue::Primitive(primitive) => Ok(primitive_string(primitive)),
+        ConjureValue::Enum(EnumValue::Known(value))
+        | ConjureValue::Enum(EnumValue::Unknown(value)) => Ok(value.clone()),
+        other => Err(E::custom(format_args!(
+            "map keys must be primitives or enums, got: {:?}",
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure::ir::PrimitiveType;
+    use conjure::resolved_type::builders::*;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    fn primitive_set(items: Vec<ConjurePrimitiveValue>) -> ConjureValue {
+        ConjureValue::Set(items.into_iter().map(ConjureValue::Primitive).collect())
+    }
+
+    fn mixed_primitives() -> Vec<ConjurePrimitiveValue> {
+        vec![
+            ConjurePrimitiveValue::Boolean(true),
+            ConjurePrimitiveValue::double(::std::f64::NAN),
+            ConjurePrimitiveValue::String("b".to_string()),
+            ConjurePrimitiveValue::Integer(10),
+            ConjurePrimitiveValue::double(-1.5),
+            ConjurePrimitiveValue::String("a".to_string()),
+            ConjurePrimitiveValue::Integer(-3),
+            ConjurePrimitiveValue::Safelong(7),
+            ConjurePrimitiveValue::Boolean(false),
+        ]
+    }
+
+    #[test]
+    fn set_order_is_stable() {
+        let forwards = primitive_set(mixed_primitives());
+        let backwards = primitive_set(mixed_primitives().into_iter().rev().collect());
+
+        let canonical = to_string(&forwards, SetOrder::Canonical).unwrap();
+        assert_eq!(canonical, r#"["a","b",-3,10,-1.5,"NaN",false,true,7]"#);
+        assert_eq!(to_string(&backwards, SetOrder::Canonical).unwrap(), canonical);
+
+        let json_text = to_string(&forwards, SetOrder::JsonText).unwrap();
+        assert_eq!(json_text, r#"["NaN","a","b",-1.5,-3,10,7,false,true]"#);
+        assert_eq!(to_string(&backwards, SetOrder::JsonText).unwrap(), json_text);
+    }
+
+    #[test]
+    fn set_order_applies_to_nested_sets() {
+        let value = ConjureValue::List(vec![primitive_set(mixed_primitives())]);
+        assert_eq!(
+            to_string(&value, SetOrder::JsonText).unwrap(),
+            r#"[["NaN","a","b",-1.5,-3,10,7,false,true]]"#
+        );
+    }
+
+    #[test]
+    fn display_string_is_truncated() {
+        let value = ConjureValue::Primitive(ConjurePrimitiveValue::String("héllo".to_string()));
+        assert_eq!(to_display_string(&value, 100), r#""héllo""#);
+        // Truncation never splits a multi-byte character.
+        assert_eq!(to_display_string(&value, 3), r#""h...<truncated>"#);
+    }
+
+    #[test]
+    fn special_doubles_are_strings() {
+        let value = ConjureValue::List(vec![
+            ConjureValue::Primitive(ConjurePrimitiveValue::double(::std::f64::INFINITY)),
+            ConjureValue::Primitive(ConjurePrimitiveValue::double(::std::f64::NEG_INFINITY)),
+            ConjureValue::Primitive(ConjurePrimitiveValue::double(::std::f64::NAN)),
+            ConjureValue::Primitive(ConjurePrimitiveValue::double(0.5)),
+        ]);
+        let rendered = to_string(&value, SetOrder::default()).unwrap();
+        assert_eq!(rendered, r#"["Infinity","-Infinity","NaN",0.5]"#);
+
+        let type_ = list_type(primitive_type(PrimitiveType::Double));
+        assert_eq!(::more_serde_json::from_str(&type_, &rendered).unwrap(), value);
+    }
+
+    #[test]
+    fn serializes_objects_unions_and_maps() {
+        let value = ConjureValue::Object(btreemap!(
+            "absent" => ConjureValue::Optional(None),
+            "union" => ConjureValue::Union(ConjureUnionValue::new(
+                UnionVariant::Known("integer".to_string()),
+                ConjureValue::Primitive(ConjurePrimitiveValue::Integer(1)).into(),
+            )),
+            "map" => ConjureValue::Map(btreemap!(
+                ConjureValue::Primitive(ConjurePrimitiveValue::Boolean(true)) =>
+                    ConjureValue::Enum(EnumValue::Known("ONE".to_string()))
+            ))
+        ));
+        assert_eq!(
+            to_string(&value, SetOrder::default()).unwrap(),
+            r#"{"map":{"true":"ONE"},"union":{"type":"integer","integer":1}}"#
+        );
+    }
+
+    #[test]
+    fn enum_values_are_serialized_verbatim() {
+        let value = ConjureValue::List(vec![
+            ConjureValue::Enum(EnumValue::Known("APPLE".to_string())),
+            ConjureValue::Enum(EnumValue::Unknown("apple".to_string())),
+        ]);
+        assert_eq!(
+            to_string(&value, SetOrder::default()).unwrap(),
+            r#"["APPLE","apple"]"#
+        );
+    }
+
+    #[test]
+    fn present_optional_collections_are_not_null() {
+        let present = |value| ConjureValue::Optional(Some(Box::new(value)));
+        let value = ConjureValue::Object(btreemap!(
+            "absent" => ConjureValue::Optional(None),
+            "list" => present(ConjureValue::List(vec![])),
+            "map" => present(ConjureValue::Map(BTreeMap::new())),
+            "set" => present(ConjureValue::Set(BTreeSet::new()))
+        ));
+        let rendered = to_string(&value, SetOrder::default()).unwrap();
+        assert_eq!(rendered, r#"{"list":[],"map":{},"set":[]}"#);
+
+        let type_ = object_definition(
+            "Collections",
+            &[
+                field_definition(
+                    "absent",
+                    optional_type(primitive_type(PrimitiveType::Integer)),
+                ),
+                field_definition(
+                    "list",
+                    optional_type(list_type(primitive_type(PrimitiveType::Integer))),
+                ),
+                field_definition(
+                    "map",
+                    optional_type(map_type(
+                        primitive_type(PrimitiveType::String),
+                        primitive_type(PrimitiveType::Integer),
+                    )),
+                ),
+                field_definition(
+                    "set",
+                    optional_type(set_type(primitive_type(PrimitiveType::Integer))),
+                ),
+            ],
+        );
+        assert_eq!(
+            ::more_serde_json::from_str(&type_, &rendered).unwrap(),
+            value
+        );
+    }
+}
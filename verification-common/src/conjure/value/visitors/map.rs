@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use conjure::ir::PrimitiveType;
 use conjure::resolved_type::ResolvedType;
+use conjure::value::de::ProfiledType;
 use conjure::value::de_plain::deserialize_plain;
 use conjure::value::*;
 use core::fmt;
@@ -26,11 +28,10 @@ use serde_json;
 use std::collections::btree_map;
 use std::collections::BTreeMap;
 
-/// This visitor also supports being visited as an option using `Deserializer::deserialize_option`,
-/// whereby it will return a default.
+/// An absent field of this type defaults to an empty map, but an explicit null is rejected.
 pub struct ConjureMapVisitor<'a> {
-    pub key_type: &'a ResolvedType,
-    pub value_type: &'a ResolvedType,
+    pub key_type: ProfiledType<'a>,
+    pub value_type: ProfiledType<'a>,
 }
 
 impl<'de: 'a, 'a> Visitor<'de> for ConjureMapVisitor<'a> {
@@ -40,13 +41,6 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureMapVisitor<'a> {
         formatter.write_str("map")
     }
 
-    fn visit_none<E>(self) -> Result<Self::Value, E>
-    where
-        E: Error,
-    {
-        Ok(BTreeMap::new())
-    }
-
     fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
         D: Deserializer<'de>,
@@ -81,8 +75,11 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureMapVisitor<'a> {
 /// A map key is a conjure [ResolvedType] that should be deserialized only from a string
 /// representation.
 ///
+/// UUID keys are checked against the profile's [UuidPolicy], like UUID values are.
+///
 /// [ResolvedType]: ../../../ir/enum.ResolvedType.html
-pub struct MapKey<'a>(&'a ResolvedType);
+/// [UuidPolicy]: ../../canonical_uuid/enum.UuidPolicy.html
+pub struct MapKey<'a>(ProfiledType<'a>);
 
 impl<'de: 'a, 'a> DeserializeSeed<'de> for MapKey<'a> {
     type Value = ConjureValue;
@@ -97,6 +94,15 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for MapKey<'a> {
         // Deserializers that don't support visit_borrowed_str (like `&serde_json::Value`)
         let str = String::deserialize(deserializer).map_err(|e| serde::de::Error::custom(e))?;
 
-        deserialize_plain(self.0, str.as_str()).map_err(|e| serde::de::Error::custom(e))
+        match *self.0.type_ {
+            ResolvedType::Primitive(PrimitiveType::Uuid) => self
+                .0
+                .uuids
+                .parse(&str)
+                .map(|uuid| ConjureValue::Primitive(ConjurePrimitiveValue::Uuid(uuid)))
+                .map_err(|e| serde::de::Error::custom(e)),
+            _ => deserialize_plain(self.0.type_, str.as_str())
+                .map_err(|e| serde::de::Error::custom(e)),
+        }
     }
 }
@@ -16,12 +16,15 @@ pub use serde::de::DeserializeSeed;
 
 use conjure::resolved_type::FieldDefinition;
 use conjure::resolved_type::ResolvedType;
+use conjure::value::de::ProfiledType;
 use conjure::value::*;
 use core::fmt;
 use itertools::Itertools;
 use serde;
 use serde::de::Error;
+use serde::de::IgnoredAny;
 use serde::de::MapAccess;
+use serde::de::SeqAccess;
 use serde::de::Visitor;
 use serde::Deserializer;
 use std::collections::BTreeMap;
@@ -30,17 +33,21 @@ use std::marker::PhantomData;
 
 pub struct ConjureObjectVisitor<'a> {
     pub remaining_fields: HashMap<&'a str, &'a ResolvedType>,
-    pub skip_unknown: bool,
+    /// The object being deserialized, whose settings also apply to its fields.
+    pub object_type: ProfiledType<'a>,
 }
 
 impl<'a> ConjureObjectVisitor<'a> {
-    pub fn new(fields: &'a [FieldDefinition], skip_unknown: bool) -> ConjureObjectVisitor {
+    pub fn new(
+        fields: &'a [FieldDefinition],
+        object_type: ProfiledType<'a>,
+    ) -> ConjureObjectVisitor<'a> {
         ConjureObjectVisitor {
             remaining_fields: fields
                 .iter()
                 .map(|FieldDefinition { field_name, type_ }| (&**field_name, type_))
                 .collect(),
-            skip_unknown,
+            object_type,
         }
     }
 }
@@ -64,24 +71,44 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureObjectVisitor<'a> {
         while let Some(key) = items.next_key::<String>()? {
             let field_type = self.remaining_fields.remove(key.as_str());
             if let Some(field_type) = field_type {
-                let value = items.next_value_seed(field_type)?;
+                let value = items
+                    .next_value_seed(self.object_type.child(field_type))
+                    .map_err(|e| A::Error::custom(format_args!("field `{}`: {}", key, e)))?;
+                // The field is present, so an empty optional means it was explicitly null.
+                if self.object_type.forbid_null_optionals && value == ConjureValue::Optional(None)
+                {
+                    return Err(A::Error::custom(format_args!(
+                        "field `{}`: expected an absent optional to be omitted, not null",
+                        key
+                    )));
+                }
                 if result.insert(key.to_string(), value).is_some() {
                     return Err(serde::de::Error::custom(format_args!(
                         "duplicate field `{}`",
                         key
                     )));
                 }
-            } else if !self.skip_unknown {
+            } else if result.contains_key(key.as_str()) {
+                return Err(serde::de::Error::custom(format_args!(
+                    "duplicate field `{}`",
+                    key
+                )));
+            } else if self.object_type.profile.skip_unknown_fields() {
+                items.next_value::<IgnoredAny>()?;
+            } else {
                 return Err(unknown_field(&key.to_string(), known_fields));
             }
         }
-        // Handle missing fields.
+        // Handle missing fields, which a partial update leaves out.
+        if self.object_type.partial {
+            return Ok(result);
+        }
         for (field_name, field_type) in self.remaining_fields {
             let deserializer = MissingFieldDeserializer(field_name, PhantomData);
             // This will succeed with an appropriate default value if the field type defines such
-            // a default value (namely - its visitor accepts `visit_none` to indicate an explicit
-            // value was missing), or otherwise fail with a 'missing field' error.
-            let value = field_type.deserialize(deserializer)?;
+            // a default value (namely optionals and collections), or otherwise fail with a
+            // 'missing field' error.
+            let value = self.object_type.child(field_type).deserialize(deserializer)?;
             result.insert(field_name.to_string(), value);
         }
         Ok(result)
@@ -106,9 +133,11 @@ fn unknown_field<'a, E: Error>(field: &'a str, expected: Vec<&'a str>) -> E {
 
 /// A Deserializer for a specific field whose value was not present in the map.
 ///
-/// When asked to deserialize an option, seq or map, it will `visit_none` on the visitor (because we
-/// expect the Conjure visitors to handle these cases with default values), but will fail with a
-/// missing field exception otherwise.
+/// When asked to deserialize an option it will `visit_none`, and when asked to deserialize a seq
+/// or map it will visit an empty one, but will fail with a missing field exception otherwise.
+///
+/// Collections are visited as empty rather than as none so that their visitors can reject an
+/// explicit null.
 struct MissingFieldDeserializer<'a, E>(&'a str, PhantomData<E>);
 
 impl<'de: 'a, 'a, E> Deserializer<'de> for MissingFieldDeserializer<'a, E>
@@ -135,14 +164,14 @@ where
     where
         V: Visitor<'de>,
     {
-        visitor.visit_none()
+        visitor.visit_seq(Empty(PhantomData))
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_none()
+        visitor.visit_map(Empty(PhantomData))
     }
 
     forward_to_deserialize_any! {
@@ -151,3 +180,41 @@ where
         tuple_struct struct identifier ignored_any
     }
 }
+
+/// An empty seq or map.
+struct Empty<E>(PhantomData<E>);
+
+impl<'de, E> SeqAccess<'de> for Empty<E>
+where
+    E: Error,
+{
+    type Error = E;
+
+    fn next_element_seed<T>(&mut self, _: T) -> Result<Option<T::Value>, E>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Ok(None)
+    }
+}
+
+impl<'de, E> MapAccess<'de> for Empty<E>
+where
+    E: Error,
+{
+    type Error = E;
+
+    fn next_key_seed<K>(&mut self, _: K) -> Result<Option<K::Value>, E>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, _: V) -> Result<V::Value, E>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Err(Error::custom("no values in an empty map"))
+    }
+}
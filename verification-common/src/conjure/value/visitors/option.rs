@@ -20,7 +20,7 @@
 //! the deserialization process (contextual deserialization) instead of the usual context-free
 //! deserialization.
 
-use conjure::resolved_type::ResolvedType;
+use conjure::value::de::ProfiledType;
 use conjure::value::*;
 use core::fmt;
 pub use serde::de::DeserializeSeed;
@@ -28,7 +28,7 @@ use serde::de::Visitor;
 use serde::Deserializer;
 use std::error::Error as StdError;
 
-pub struct ConjureOptionVisitor<'a>(pub &'a ResolvedType);
+pub struct ConjureOptionVisitor<'a>(pub ProfiledType<'a>);
 
 impl<'de: 'a, 'a> Visitor<'de> for ConjureOptionVisitor<'a> {
     type Value = Option<ConjureValue>;
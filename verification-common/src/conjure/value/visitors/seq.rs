@@ -12,18 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use conjure::resolved_type::ResolvedType;
+use conjure::value::de::ProfiledType;
 use conjure::value::*;
 use serde::de::SeqAccess;
 use serde::de::Visitor;
 use serde::private::de::size_hint;
 use serde::Deserializer;
-use std::error::Error;
 use std::fmt;
 
-/// This visitor also supports being visited as an option using `Deserializer::deserialize_option`,
-/// whereby it will return a default.
-pub struct ConjureSeqVisitor<'a>(pub &'a ResolvedType);
+/// An absent field of this type defaults to an empty list, but an explicit null is rejected.
+pub struct ConjureSeqVisitor<'a>(pub ProfiledType<'a>);
 
 impl<'de: 'a, 'a> Visitor<'de> for ConjureSeqVisitor<'a> {
     type Value = Vec<ConjureValue>;
@@ -32,13 +30,6 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureSeqVisitor<'a> {
         formatter.write_str("list")
     }
 
-    fn visit_none<E>(self) -> Result<Self::Value, E>
-    where
-        E: Error,
-    {
-        Ok(vec![])
-    }
-
     fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
         D: Deserializer<'de>,
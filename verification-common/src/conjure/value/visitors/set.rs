@@ -14,7 +14,7 @@
 
 pub use serde::de::DeserializeSeed;
 
-use conjure::resolved_type::ResolvedType;
+use conjure::value::de::ProfiledType;
 use conjure::value::*;
 use core::fmt;
 use serde::de::Error;
@@ -24,10 +24,9 @@ use serde::Deserializer;
 use serde_json;
 use std::collections::BTreeSet;
 
-/// This visitor also supports being visited as an option using `Deserializer::deserialize_option`,
-/// whereby it will return a default.
+/// An absent field of this type defaults to an empty set, but an explicit null is rejected.
 pub struct ConjureSetVisitor<'a> {
-    pub item_type: &'a ResolvedType,
+    pub item_type: ProfiledType<'a>,
     pub fail_on_duplicates: bool,
 }
 
@@ -38,13 +37,6 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureSetVisitor<'a> {
         formatter.write_str("a sequence")
     }
 
-    fn visit_none<E>(self) -> Result<Self::Value, E>
-    where
-        E: Error,
-    {
-        Ok(BTreeSet::new())
-    }
-
     fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
         D: Deserializer<'de>,
@@ -16,6 +16,7 @@ use conjure::ir::PrimitiveType;
 use conjure::resolved_type::builders::*;
 use conjure::resolved_type::FieldDefinition;
 use conjure::resolved_type::UnionDefinition;
+use conjure::value::de::ProfiledType;
 use conjure::value::*;
 use serde::de::DeserializeSeed;
 use serde::de::Error;
@@ -26,7 +27,9 @@ use serde::Deserializer;
 use serde_conjure::UnionTypeField;
 use std::fmt;
 
-pub struct ConjureUnionVisitor<'a>(pub &'a UnionDefinition);
+/// Visits the union with the given definition, deserializing its value with the same settings as
+/// the union itself.
+pub struct ConjureUnionVisitor<'a>(pub &'a UnionDefinition, pub ProfiledType<'a>);
 
 impl<'de: 'a, 'a> Visitor<'de> for ConjureUnionVisitor<'a> {
     type Value = ConjureUnionValue;
@@ -46,7 +49,7 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureUnionVisitor<'a> {
                 match key {
                     Some(UnionField::Data(ref union_variant)) => {
                         fail_if_mismatching_variant(&variant, union_variant)?;
-                        build_union_value(&mut items, union_variant)
+                        build_union_value(&mut items, union_variant, self.1)
                     }
                     Some(UnionField::Type) | None => {
                         Err(Error::custom(format_args!("missing field `{}`", variant)))
@@ -54,7 +57,7 @@ impl<'de: 'a, 'a> Visitor<'de> for ConjureUnionVisitor<'a> {
                 }
             }
             Some(UnionField::Data(ref union_variant)) => {
-                let result = build_union_value(&mut items, union_variant);
+                let result = build_union_value(&mut items, union_variant, self.1);
                 if items.next_key::<UnionTypeField>()?.is_none() {
                     return Err(Error::missing_field("type"));
                 }
@@ -127,6 +130,7 @@ impl<'de: 'a, 'a> DeserializeSeed<'de> for &'a UnionDefinition {
 fn build_union_value<'de: 'a, 'a, A>(
     items: &mut A,
     union_variant: &UnionVariantInner,
+    union_type: ProfiledType,
 ) -> Result<ConjureUnionValue, A::Error>
 where
     A: MapAccess<'de>,
@@ -134,13 +138,13 @@ where
     Ok(match union_variant {
         UnionVariantInner::Real(FieldDefinition { type_, field_name }) => ConjureUnionValue {
             variant: UnionVariant::Known(field_name.clone()),
-            value: items.next_value_seed(type_)?.into(),
+            value: items.next_value_seed(union_type.child(type_))?.into(),
         },
         UnionVariantInner::Unknown(field_name) => ConjureUnionValue {
             variant: UnionVariant::Unknown(field_name.clone()),
             // deserialize it as 'any'
             value: items
-                .next_value_seed(&primitive_type(PrimitiveType::Any))?
+                .next_value_seed(union_type.child(&primitive_type(PrimitiveType::Any)))?
                 .into(),
         },
     })
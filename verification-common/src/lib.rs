@@ -22,6 +22,8 @@ extern crate serde_derive;
 extern crate derive_more;
 #[macro_use]
 extern crate derive_new;
+#[macro_use]
+extern crate log;
 
 extern crate base64;
 extern crate chrono;
@@ -30,6 +32,7 @@ extern crate core;
 extern crate either;
 extern crate itertools;
 extern crate serde_conjure;
+extern crate serde_cbor;
 #[cfg_attr(test, macro_use)]
 extern crate serde_json;
 extern crate serde_plain;
@@ -40,6 +43,11 @@ extern crate uuid;
 #[macro_use]
 pub mod macros;
 pub mod conjure;
+pub mod more_serde_cbor;
 pub mod more_serde_json;
+pub mod ndjson;
 pub mod test_spec;
+#[cfg(any(test, feature = "test-support"))]
+#[macro_use]
+pub mod test_support;
 pub mod type_mapping;
@@ -0,0 +1,57 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The CBOR counterpart of [more_serde_json](../more_serde_json/index.html), for contextual
+//! deserialization of CBOR bodies.
+
+use serde::de::DeserializeSeed;
+use serde_cbor;
+
+/// Deserializes a single CBOR item from the slice, rejecting any bytes that follow it.
+pub fn from_slice<'de: 'a, 'a, T>(seed: T, slice: &'de [u8]) -> serde_cbor::Result<T::Value>
+where
+    T: DeserializeSeed<'de>,
+{
+    let mut de = serde_cbor::Deserializer::from_slice(slice);
+    let value = seed.deserialize(&mut de)?;
+
+    // Make sure the whole slice has been consumed.
+    de.end()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure::ir::PrimitiveType;
+    use conjure::resolved_type::builders::*;
+    use conjure::value::*;
+
+    #[test]
+    fn rejects_trailing_data() {
+        let type_ = list_type(primitive_type(PrimitiveType::Integer));
+        let mut cbor = serde_cbor::to_vec(&json!([1, 2])).unwrap();
+        assert_eq!(
+            from_slice(&type_, &cbor).unwrap(),
+            ConjureValue::List(vec![
+                ConjureValue::Primitive(ConjurePrimitiveValue::Integer(1)),
+                ConjureValue::Primitive(ConjurePrimitiveValue::Integer(2)),
+            ])
+        );
+
+        // A second, complete item is still trailing data.
+        cbor.extend(serde_cbor::to_vec(&json!(3)).unwrap());
+        assert!(from_slice(&type_, &cbor).is_err());
+    }
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Two helpers adapted from serde_json to allow us to do contextual deserialization into our
+//! Helpers adapted from serde_json to allow us to do contextual deserialization into our
 //! conjure::value::ConjureValue type, where serde_json only provides helpers to do
 //! context-free deserializing.
 //!
@@ -28,6 +28,15 @@ where
     from_trait(seed, serde_json::de::StrRead::new(str))
 }
 
+/// Like `from_str`, but reads directly from a byte slice, avoiding the need to first validate it
+/// as UTF-8 and copy it into a `String`.
+pub fn from_slice<'de: 'a, 'a, T>(seed: T, slice: &'de [u8]) -> serde_json::Result<T::Value>
+where
+    T: DeserializeSeed<'de>,
+{
+    from_trait(seed, serde_json::de::SliceRead::new(slice))
+}
+
 pub fn from_trait<'de: 'a, 'a, R, T>(seed: T, read: R) -> serde_json::Result<T::Value>
 where
     R: serde_json::de::Read<'de>,
@@ -0,0 +1,119 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Newline-delimited JSON (NDJSON), which streams a sequence of Conjure values as one compact JSON
+//! document per line.
+
+use conjure::value::de::ProfiledType;
+use conjure::value::ser::ConjureJson;
+use conjure::value::ConjureValue;
+use more_serde_json;
+use serde::de::Error;
+use serde_json;
+use std::collections::BTreeSet;
+use std::io::Write;
+
+/// The media type of an NDJSON body.
+pub const APPLICATION_NDJSON: &str = "application/x-ndjson";
+
+/// Writes each value as Conjure JSON followed by a newline.
+pub fn to_writer<'a, W, I>(mut writer: W, values: I) -> serde_json::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a ConjureValue>,
+{
+    for value in values {
+        serde_json::to_writer(&mut writer, &ConjureJson::new(value))?;
+        writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+    }
+    Ok(())
+}
+
+/// Deserializes every line of the slice as a value of `item_type`, skipping blank lines.
+pub fn from_slice(item_type: ProfiledType, slice: &[u8]) -> serde_json::Result<Vec<ConjureValue>> {
+    slice
+        .split(|b| *b == b'\n')
+        .map(|line| match line.last() {
+            Some(b'\r') => &line[..line.len() - 1],
+            _ => line,
+        }).filter(|line| !line.is_empty())
+        .map(|line| more_serde_json::from_slice(item_type, line))
+        .collect()
+}
+
+/// Like [from_slice], but collects the values into a set, failing on duplicates if the profile of
+/// `item_type` requires it, as deserializing a JSON array as a set would.
+pub fn set_from_slice(
+    item_type: ProfiledType,
+    slice: &[u8],
+) -> serde_json::Result<BTreeSet<ConjureValue>> {
+    let mut values = BTreeSet::new();
+    for value in from_slice(item_type, slice)? {
+        if item_type.profile.fail_on_set_duplicates() && values.contains(&value) {
+            return Err(serde_json::Error::custom(format_args!(
+                "Set contained duplicates: {}",
+                serde_json::ser::to_string(&value).unwrap()
+            )));
+        }
+        values.insert(value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure::ir::PrimitiveType;
+    use conjure::resolved_type::builders::*;
+    use conjure::value::profile::StrictnessProfile;
+    use conjure::value::*;
+
+    #[test]
+    fn round_trips_values() {
+        let item_type = object_definition(
+            "Item",
+            &[field_definition("id", primitive_type(PrimitiveType::Integer))],
+        );
+        let values = (1..4)
+            .map(|id| {
+                ConjureValue::Object(btreemap!(
+                    "id" => ConjureValue::Primitive(ConjurePrimitiveValue::Integer(id))
+                ))
+            }).collect::<Vec<_>>();
+
+        let mut ndjson = vec![];
+        to_writer(&mut ndjson, &values).unwrap();
+        assert_eq!(&ndjson[..], &b"{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n"[..]);
+
+        let item_type = item_type.with_profile(StrictnessProfile::Client);
+        assert_eq!(from_slice(item_type, &ndjson).unwrap(), values);
+        // Blank lines and CRLF line endings are tolerated.
+        let crlf = b"{\"id\":1}\r\n\r\n{\"id\":2}\r\n{\"id\":3}";
+        assert_eq!(from_slice(item_type, crlf).unwrap(), values);
+        assert!(from_slice(item_type, b"{\"id\":1} {\"id\":2}").is_err());
+    }
+
+    #[test]
+    fn set_duplicates_depend_on_profile() {
+        let item_type = primitive_type(PrimitiveType::Integer);
+        let ndjson = b"1\n2\n1\n";
+
+        let lenient = set_from_slice(item_type.with_profile(StrictnessProfile::Client), ndjson);
+        let expected = (1..3)
+            .map(|i| ConjureValue::Primitive(ConjurePrimitiveValue::Integer(i)))
+            .collect::<BTreeSet<_>>();
+        assert_eq!(lenient.unwrap(), expected);
+        assert!(set_from_slice(item_type.with_profile(StrictnessProfile::Server), ndjson).is_err());
+    }
+}
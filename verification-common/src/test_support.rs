@@ -0,0 +1,88 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for tests that compare `ConjureValue`s.
+//!
+//! Only built for this crate's tests, or for other crates' with the `test-support` feature.
+
+use conjure::value::diff::first_difference;
+use conjure::value::ser::{self, SetOrder};
+use conjure::value::ConjureValue;
+
+/// Asserts that two `ConjureValue`s are equal.
+///
+/// On mismatch, panics with both values rendered as canonical Conjure JSON, along with the path of
+/// the first difference between them.
+#[macro_export]
+macro_rules! assert_conjure_eq {
+    ($left:expr, $right:expr) => {{
+        if let Some(message) = $crate::test_support::conjure_mismatch(&$left, &$right) {
+            panic!("{}", message);
+        }
+    }};
+}
+
+/// Describes how `left` differs from `right`, or returns `None` if they're equal.
+pub fn conjure_mismatch(left: &ConjureValue, right: &ConjureValue) -> Option<String> {
+    let path = first_difference(left, right)?;
+    let render = |value: &ConjureValue| {
+        ser::to_string(value, SetOrder::Canonical).unwrap_or_else(|_| format!("{:?}", value))
+    };
+    Some(format!(
+        "assertion failed: `(left == right)`\n first difference: {}\n  left: {}\n right: {}",
+        path,
+        render(left),
+        render(right)
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure::value::ConjurePrimitiveValue;
+
+    fn object(foo: Vec<i32>) -> ConjureValue {
+        ConjureValue::Object(btreemap!(
+            "foo" => ConjureValue::List(
+                foo.into_iter()
+                    .map(|value| ConjureValue::Primitive(ConjurePrimitiveValue::Integer(value)))
+                    .collect()
+            ),
+            "bar" => ConjureValue::Optional(None)
+        ))
+    }
+
+    #[test]
+    fn equal_values() {
+        assert_conjure_eq!(object(vec![1, 2]), object(vec![1, 2]));
+        assert_eq!(conjure_mismatch(&object(vec![1, 2]), &object(vec![1, 2])), None);
+    }
+
+    #[test]
+    fn mismatch_shows_json_and_path() {
+        assert_eq!(
+            conjure_mismatch(&object(vec![1, 2]), &object(vec![1, 3])).unwrap(),
+            "assertion failed: `(left == right)`\n \
+             first difference: $.foo[1]\n  \
+             left: {\"foo\":[1,2]}\n \
+             right: {\"foo\":[1,3]}"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "first difference: $.foo[1]")]
+    fn macro_panics_on_mismatch() {
+        assert_conjure_eq!(object(vec![1, 2]), object(vec![1, 3]));
+    }
+}
@@ -23,11 +23,12 @@
 use conjure::ir;
 use conjure::ir::Conjure;
 use conjure::resolved_type::ResolvedType;
-use conjure::type_resolution::resolve_type;
+use conjure::type_resolution;
+use conjure_verification_error::Result;
 use std::collections::HashMap;
 use test_spec::EndpointName;
 
-#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+#[derive(ConjureSerialize, Eq, PartialEq, Hash, Clone, Debug)]
 /// The types of tests that you can run.
 pub enum TestType {
     Body,
@@ -71,7 +72,8 @@ pub fn resolve_types<'a, 'b>(
                 let mut endpoint_map = HashMap::new();
                 for e in &service.endpoints {
                     // Resolve aliases
-                    let type_ = resolve_type(&ir.types, type_for_endpoint_fn(&e));
+                    let type_ =
+                        type_resolution::resolve_type(&ir.types, type_for_endpoint_fn(&e));
                     // Create a unique map
                     assert!(
                         endpoint_map
@@ -92,6 +94,11 @@ pub fn resolve_types<'a, 'b>(
     param_types
 }
 
+/// Resolves a single type from the IR, looking up any references in the IR's type definitions.
+pub fn resolve_type(ir: &Conjure, t: &ir::Type) -> Result<ResolvedType> {
+    type_resolution::try_resolve_type(&ir.types, t)
+}
+
 pub fn type_of_non_index_arg(endpoint_def: &ir::EndpointDefinition) -> &ir::Type {
     &endpoint_def
         .args
@@ -135,3 +142,85 @@ pub mod builder {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure::ir::PrimitiveType;
+    use conjure::resolved_type::builders::*;
+
+    fn ir() -> Conjure {
+        Conjure {
+            types: vec![
+                ir::TypeDefinition::Alias(ir::AliasDefinition {
+                    type_name: type_name("StringAlias"),
+                    alias: ir::Type::Primitive(PrimitiveType::String).into(),
+                }),
+                ir::TypeDefinition::Object(ir::ObjectDefinition {
+                    type_name: type_name("Foo"),
+                    fields: vec![ir::FieldDefinition {
+                        field_name: "bar".to_string(),
+                        type_: ir::Type::Reference(type_name("StringAlias")).into(),
+                    }],
+                }),
+            ],
+            services: vec![],
+        }
+    }
+
+    #[test]
+    fn resolves_reference_type() {
+        let resolved = resolve_type(&ir(), &ir::Type::Reference(type_name("Foo"))).unwrap();
+        assert_eq!(
+            resolved,
+            object_definition(
+                "Foo",
+                &[field_definition("bar", primitive_type(PrimitiveType::String))]
+            )
+        );
+
+        let error = resolve_type(&ir(), &ir::Type::Reference(type_name("Missing"))).unwrap_err();
+        assert_eq!(
+            error.safe_params()["typeName"],
+            "com.palantir.package.Missing"
+        );
+    }
+
+    #[test]
+    fn serializes_param_types() {
+        let mut param_types = builder::ParamTypesBuilder::default();
+        param_types.add(
+            TestType::Body,
+            EndpointName::new("receiveFoo"),
+            resolve_type(&ir(), &ir::Type::Reference(type_name("Foo"))).unwrap(),
+        );
+        assert_eq!(
+            ::serde_json::to_value(param_types.build()).unwrap(),
+            json!({
+                "BODY": {
+                    "receiveFoo": {
+                        "type": "object",
+                        "object": {
+                            "typeName": {"name": "Foo", "package": "com.palantir.package"},
+                            "fields": [{
+                                "fieldName": "bar",
+                                "type": {"type": "primitive", "primitive": "STRING"},
+                            }],
+                        },
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn resolves_anonymous_list_type() {
+        let type_ = ir::Type::List(ir::ListType {
+            item_type: ir::Type::Reference(type_name("StringAlias")).into(),
+        });
+        assert_eq!(
+            resolve_type(&ir(), &type_).unwrap(),
+            list_type(primitive_type(PrimitiveType::String))
+        );
+    }
+}
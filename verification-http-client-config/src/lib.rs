@@ -95,6 +95,12 @@ impl ServiceDiscoveryConfig {
             {
                 service.experimental_assume_http2(experimental_assume_http2);
             }
+            if let Some(throttle_switches_node) = raw_service
+                .throttle_switches_node
+                .or(raw.throttle_switches_node)
+            {
+                service.throttle_switches_node(throttle_switches_node);
+            }
 
             config.service(&name, service.build());
         }
@@ -140,6 +146,7 @@ pub struct ServiceConfig {
     proxy: ProxyConfig,
     keep_alive: bool,
     experimental_assume_http2: bool,
+    throttle_switches_node: bool,
 }
 
 impl Default for ServiceConfig {
@@ -155,6 +162,7 @@ impl Default for ServiceConfig {
             max_num_retries: 3,
             keep_alive: true,
             experimental_assume_http2: false,
+            throttle_switches_node: false,
         }
     }
 }
@@ -203,6 +211,72 @@ impl ServiceConfig {
     pub fn experimental_assume_http2(&self) -> bool {
         self.experimental_assume_http2
     }
+
+    /// Whether a request throttled with a 429 is retried against the next node rather than the
+    /// same one.
+    ///
+    /// Switching nodes helps when each node throttles independently, but only adds load elsewhere
+    /// when the throttling is global, so it is disabled by default.
+    pub fn throttle_switches_node(&self) -> bool {
+        self.throttle_switches_node
+    }
+
+    /// Returns a config taking each value that differs from the default from this config, and
+    /// every other value from `base`.
+    ///
+    /// A value explicitly set to its default is indistinguishable from an unset one, so it is also
+    /// taken from `base`.
+    pub fn overlay(&self, base: &ServiceConfig) -> ServiceConfig {
+        fn pick<T>(value: &T, default: &T, base: &T) -> T
+        where
+            T: PartialEq + Clone,
+        {
+            if value == default {
+                base.clone()
+            } else {
+                value.clone()
+            }
+        }
+
+        let default = ServiceConfig::default();
+        ServiceConfig {
+            uris: pick(&self.uris, &default.uris, &base.uris),
+            security: pick(&self.security, &default.security, &base.security),
+            connect_timeout: pick(
+                &self.connect_timeout,
+                &default.connect_timeout,
+                &base.connect_timeout,
+            ),
+            read_timeout: pick(&self.read_timeout, &default.read_timeout, &base.read_timeout),
+            write_timeout: pick(
+                &self.write_timeout,
+                &default.write_timeout,
+                &base.write_timeout,
+            ),
+            max_num_retries: pick(
+                &self.max_num_retries,
+                &default.max_num_retries,
+                &base.max_num_retries,
+            ),
+            backoff_slot_size: pick(
+                &self.backoff_slot_size,
+                &default.backoff_slot_size,
+                &base.backoff_slot_size,
+            ),
+            proxy: pick(&self.proxy, &default.proxy, &base.proxy),
+            keep_alive: pick(&self.keep_alive, &default.keep_alive, &base.keep_alive),
+            experimental_assume_http2: pick(
+                &self.experimental_assume_http2,
+                &default.experimental_assume_http2,
+                &base.experimental_assume_http2,
+            ),
+            throttle_switches_node: pick(
+                &self.throttle_switches_node,
+                &default.throttle_switches_node,
+                &base.throttle_switches_node,
+            ),
+        }
+    }
 }
 
 pub struct ServiceConfigBuilder(ServiceConfig);
@@ -270,6 +344,11 @@ impl ServiceConfigBuilder {
         self
     }
 
+    pub fn throttle_switches_node(&mut self, throttle_switches_node: bool) -> &mut Self {
+        self.0.throttle_switches_node = throttle_switches_node;
+        self
+    }
+
     pub fn build(&self) -> ServiceConfig {
         self.0.clone()
     }
@@ -37,6 +37,7 @@ pub struct ServiceDiscoveryConfig {
     pub backoff_slot_size: Option<Duration>,
     pub keep_alive: Option<bool>,
     pub experimental_assume_http2: Option<bool>,
+    pub throttle_switches_node: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -114,6 +115,8 @@ pub struct ServiceConfig {
     pub keep_alive: Option<bool>,
     #[serde(default)]
     pub experimental_assume_http2: Option<bool>,
+    #[serde(default)]
+    pub throttle_switches_node: Option<bool>,
 }
 
 fn de_urls<'de, D>(d: D) -> Result<Vec<Url>, D::Error>
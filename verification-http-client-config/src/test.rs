@@ -71,7 +71,8 @@ fn root_defaults() {
             "connect-timeout": "10 seconds",
             "read-timeout": "11 seconds",
             "write-timeout": "12 seconds",
-            "keep-alive": true
+            "keep-alive": true,
+            "throttle-switches-node": true
         }
     "#;
     let config = serde_json::from_str::<ServiceDiscoveryConfig>(config).unwrap();
@@ -93,6 +94,7 @@ fn root_defaults() {
                 .read_timeout(Duration::from_secs(11))
                 .write_timeout(Duration::from_secs(12))
                 .keep_alive(true)
+                .throttle_switches_node(true)
                 .build(),
         ).build();
     assert_eq!(config, expected);
@@ -117,7 +119,8 @@ fn service_overrides() {
                     "connect-timeout": "13 seconds",
                     "read-timeout": "14 seconds",
                     "write-timeout": "15 seconds",
-                    "keep-alive": false
+                    "keep-alive": false,
+                    "throttle-switches-node": false
                 }
             },
             "security": {
@@ -134,7 +137,8 @@ fn service_overrides() {
             "connect-timeout": "10 seconds",
             "read-timeout": "11 seconds",
             "write-timeout": "12 seconds",
-            "keep-alive": true
+            "keep-alive": true,
+            "throttle-switches-node": true
         }
     "#;
     let config = serde_json::from_str::<ServiceDiscoveryConfig>(config).unwrap();
@@ -155,7 +159,45 @@ fn service_overrides() {
                 .read_timeout(Duration::from_secs(14))
                 .write_timeout(Duration::from_secs(15))
                 .keep_alive(false)
+                .throttle_switches_node(false)
                 .build(),
         ).build();
     assert_eq!(config, expected);
 }
+
+#[test]
+fn overlay() {
+    let base = ServiceConfig::builder()
+        .uris(vec!["http://base.com".parse().unwrap()])
+        .security(
+            SecurityConfig::builder()
+                .ca_file(Some("/foo/bar".into()))
+                .build(),
+        ).connect_timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(11))
+        .max_num_retries(5)
+        .throttle_switches_node(true)
+        .build();
+    let service = ServiceConfig::builder()
+        .uris(vec!["http://service.com".parse().unwrap()])
+        .connect_timeout(Duration::from_secs(13))
+        .write_timeout(Duration::from_secs(15))
+        .keep_alive(false)
+        .build();
+
+    let expected = ServiceConfig::builder()
+        .uris(vec!["http://service.com".parse().unwrap()])
+        .security(
+            SecurityConfig::builder()
+                .ca_file(Some("/foo/bar".into()))
+                .build(),
+        ).connect_timeout(Duration::from_secs(13))
+        .read_timeout(Duration::from_secs(11))
+        .write_timeout(Duration::from_secs(15))
+        .max_num_retries(5)
+        .keep_alive(false)
+        .throttle_switches_node(true)
+        .build();
+    assert_eq!(service.overlay(&base), expected);
+    assert_eq!(ServiceConfig::default().overlay(&base), base);
+}
@@ -19,11 +19,9 @@ use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::{Body, Method, Request, Version};
 use state_machine_future::RentToOwn;
 use std::error::Error;
-use tokio::net::TcpStream;
-use tokio_io_timeout::TimeoutStream;
 use typed_headers::{HeaderMapExt, Host, ProxyAuthorization};
 
-use async::socket::{SocketConnectFuture, SocketConnector};
+use async::socket::{SocketConnectFuture, SocketConnector, TimeoutTcpStream};
 
 #[derive(Clone)]
 pub struct ProxyConnectorConfig {
@@ -43,7 +41,7 @@ impl ProxyConnector {
 }
 
 impl Connect for ProxyConnector {
-    type Transport = TimeoutStream<TcpStream>;
+    type Transport = TimeoutTcpStream;
     type Error = Box<Error + Sync + Send>;
     type Future = ProxyConnectFuture;
 
@@ -75,17 +73,17 @@ pub enum ProxyConnect {
     },
     #[state_machine_future(transitions(TunnelConnecting))]
     TunnelHandshaking {
-        conn: Handshake<TimeoutStream<TcpStream>, Body>,
+        conn: Handshake<TimeoutTcpStream, Body>,
         proxy: ProxyConnectorConfig,
         dst: Destination,
     },
     #[state_machine_future(transitions(Finished))]
     TunnelConnecting {
         resp: ResponseFuture,
-        conn: Connection<TimeoutStream<TcpStream>, Body>,
+        conn: Connection<TimeoutTcpStream, Body>,
     },
     #[state_machine_future(ready)]
-    Finished((TimeoutStream<TcpStream>, Connected)),
+    Finished((TimeoutTcpStream, Connected)),
     #[state_machine_future(error)]
     Failed(Box<Error + Sync + Send>),
 }
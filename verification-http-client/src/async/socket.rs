@@ -13,9 +13,11 @@
 // limitations under the License.
 
 use futures::{Async, Future, Poll};
+use hyper;
 use state_machine_future::RentToOwn;
 use std::error::Error;
-use std::io;
+use std::fmt;
+use std::io::{self, Read, Write};
 use std::net::SocketAddr;
 use std::net::ToSocketAddrs;
 use std::time::Duration;
@@ -23,9 +25,12 @@ use std::vec;
 use tokio::net::tcp::ConnectFuture;
 use tokio::net::TcpStream;
 use tokio::timer::Timeout;
+use tokio_io::{AsyncRead, AsyncWrite};
 use tokio_io_timeout::TimeoutStream;
 use tokio_threadpool;
 
+use async::custom_error::ConnectError;
+
 #[derive(Copy, Clone)]
 pub struct Timeouts {
     pub connect: Duration,
@@ -33,6 +38,93 @@ pub struct Timeouts {
     pub write: Duration,
 }
 
+/// The phase of a request that timed out.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimeoutKind {
+    /// Establishing the TCP connection took longer than the connect timeout.
+    Connect,
+    /// The server sent nothing for longer than the read timeout.
+    Read,
+    /// The server accepted nothing for longer than the write timeout.
+    Write,
+}
+
+impl fmt::Display for TimeoutKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.description())
+    }
+}
+
+impl Error for TimeoutKind {
+    fn description(&self) -> &str {
+        match *self {
+            TimeoutKind::Connect => "connection timed out",
+            TimeoutKind::Read => "read timed out",
+            TimeoutKind::Write => "write timed out",
+        }
+    }
+}
+
+impl TimeoutKind {
+    /// Returns the phase that timed out, if the error returned by a request was caused by a
+    /// timeout.
+    pub fn of(error: &(Error + 'static)) -> Option<TimeoutKind> {
+        if let Some(kind) = error.downcast_ref::<TimeoutKind>() {
+            return Some(*kind);
+        }
+        if let Some(error) = error.downcast_ref::<io::Error>() {
+            return error.get_ref().and_then(|e| TimeoutKind::of(e));
+        }
+        if let Some(error) = error.downcast_ref::<hyper::Error>() {
+            return error.cause2().and_then(|e| TimeoutKind::of(e));
+        }
+        if let Some(error) = error.downcast_ref::<ConnectError>() {
+            return TimeoutKind::of(&*error.0);
+        }
+        None
+    }
+
+    fn tag(self, error: io::Error) -> io::Error {
+        if error.kind() == io::ErrorKind::TimedOut {
+            io::Error::new(io::ErrorKind::TimedOut, self)
+        } else {
+            error
+        }
+    }
+}
+
+/// A socket with read and write timeouts, whose timeout errors record which of the two expired.
+#[derive(Debug)]
+pub struct TimeoutTcpStream(TimeoutStream<TcpStream>);
+
+impl Read for TimeoutTcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(|e| TimeoutKind::Read.tag(e))
+    }
+}
+
+impl Write for TimeoutTcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(|e| TimeoutKind::Write.tag(e))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().map_err(|e| TimeoutKind::Write.tag(e))
+    }
+}
+
+impl AsyncRead for TimeoutTcpStream {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        self.0.prepare_uninitialized_buffer(buf)
+    }
+}
+
+impl AsyncWrite for TimeoutTcpStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.0.shutdown().map_err(|e| TimeoutKind::Write.tag(e))
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct SocketConnector(pub Timeouts);
 
@@ -58,7 +150,7 @@ pub enum SocketConnect {
         timeouts: Timeouts,
     },
     #[state_machine_future(ready)]
-    Ready(TimeoutStream<TcpStream>),
+    Ready(TimeoutTcpStream),
     #[state_machine_future(error)]
     Failed(Box<Error + Sync + Send>),
 }
@@ -106,7 +198,10 @@ impl PollSocketConnect for SocketConnect {
                 Ok(Async::NotReady) => return Ok(Async::NotReady),
                 Err(e) => match e.into_inner() {
                     Some(e) => Err(e),
-                    None => Err(io::Error::new(io::ErrorKind::Other, "connection timed out")),
+                    None => Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        TimeoutKind::Connect,
+                    )),
                 },
             };
 
@@ -121,7 +216,7 @@ impl PollSocketConnect for SocketConnect {
                     stream.set_read_timeout(Some(connecting.timeouts.read));
                     stream.set_write_timeout(Some(connecting.timeouts.write));
                     debug!("connected to server, addr: {}", connecting.cur_addr);
-                    transition!(Ready(stream));
+                    transition!(Ready(TimeoutTcpStream(stream)));
                 }
                 Err(e) => {
                     debug!(
@@ -157,3 +157,63 @@ impl Body for BytesBody {
         true
     }
 }
+
+/// A `Body` consisting of a byte buffer sent with an arbitrary content type and, optionally, an
+/// arbitrary `Content-Length`.
+///
+/// By default the content length is the length of the buffer. If the content length is removed
+/// with `with_content_length(None)`, the body is streamed with chunked transfer encoding instead.
+/// The buffer is retained, so the body can always be reset.
+pub struct RawBody {
+    body: Bytes,
+    mime: Mime,
+    content_length: Option<u64>,
+}
+
+impl RawBody {
+    pub fn new<T>(body: T, mime: Mime) -> RawBody
+    where
+        T: Into<Bytes>,
+    {
+        let body = body.into();
+        RawBody {
+            content_length: Some(body.len() as u64),
+            body,
+            mime,
+        }
+    }
+
+    /// Overrides the `Content-Length` sent with the body.
+    ///
+    /// The caller is responsible for making sure that a declared length is accurate.
+    pub fn with_content_length(mut self, content_length: Option<u64>) -> RawBody {
+        self.content_length = content_length;
+        self
+    }
+}
+
+impl Body for RawBody {
+    fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    fn content_type(&self) -> Mime {
+        self.mime.clone()
+    }
+
+    fn full_body(&self) -> Option<Bytes> {
+        match self.content_length {
+            Some(_) => Some(self.body.clone()),
+            None => None,
+        }
+    }
+
+    fn write(&mut self, w: &mut Write) -> Result<()> {
+        w.write_all(&self.body).map_err(Error::internal_safe)?;
+        w.flush().map_err(Error::internal_safe)
+    }
+
+    fn reset(&mut self) -> bool {
+        true
+    }
+}
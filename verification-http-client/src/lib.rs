@@ -61,9 +61,12 @@ use openssl::error::ErrorStack;
 use openssl::ssl::{SslConnector, SslMethod};
 use std::error;
 use std::fmt;
+use std::path::Path;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::runtime::{self, Runtime};
+use tokio::runtime::Runtime;
 use typed_headers::{Credentials, ProxyAuthorization};
 use zipkin::Tracer;
 
@@ -71,11 +74,13 @@ use async::alpn::AlpnConnector;
 use async::custom_error::CustomErrorConnector;
 use async::proxy::{ProxyConnector, ProxyConnectorConfig};
 use async::socket::{SocketConnector, Timeouts};
+pub use async::socket::TimeoutKind;
 pub use body::*;
 use node_selector::NodeSelector;
 pub use reloadable::*;
 pub use request::*;
 pub use response::*;
+pub use runtime::RuntimeConfig;
 pub use user_agent::*;
 
 #[doc(inline)]
@@ -96,25 +101,14 @@ pub mod node_selector;
 pub mod reloadable;
 pub mod request;
 pub mod response;
+pub mod runtime;
 pub mod user_agent;
 
 #[cfg(test)]
 mod test;
 
 lazy_static! {
-    static ref RUNTIME: Runtime = {
-        let mut pool = tokio_threadpool::Builder::new();
-        // we use blocking for DNS lookup so we don't need/want a ton of parallelism available
-        pool.max_blocking(2)
-            .keep_alive(Some(Duration::from_secs(30)))
-            .name_prefix("chatter-");
-
-        #[allow(deprecated)]
-        runtime::Builder::new()
-            .threadpool_builder(pool)
-            .build()
-            .unwrap()
-    };
+    static ref RUNTIME: Arc<Runtime> = Arc::new(RuntimeConfig::default().build().unwrap());
     static ref APPLICATION_CBOR: Mime = "application/cbor".parse().unwrap();
 }
 
@@ -155,7 +149,11 @@ impl RemoteError {
     }
 }
 
-fn extract_config(service: &str, discovery_config: &ServiceDiscoveryConfig) -> Result<ClientState> {
+fn extract_config(
+    service: &str,
+    discovery_config: &ServiceDiscoveryConfig,
+    runtime: &Arc<Runtime>,
+) -> Result<ClientState> {
     let service_config = match discovery_config.service(service) {
         Some(service_config) => service_config,
         None => {
@@ -225,23 +223,27 @@ fn extract_config(service: &str, discovery_config: &ServiceDiscoveryConfig) -> R
         .keep_alive(service_config.keep_alive())
         .http2_only(service_config.experimental_assume_http2())
         .http1_writev(false)
-        .executor(RUNTIME.executor())
+        .executor(runtime.executor())
         .build(connector);
 
     Ok(ClientState {
         client,
+        runtime: runtime.clone(),
         nodes,
         max_num_retries: service_config.max_num_retries(),
         backoff_slot_size: service_config.backoff_slot_size(),
+        throttle_switches_node: service_config.throttle_switches_node(),
         proxy: proxy_state,
     })
 }
 
 struct ClientState {
     client: hyper::Client<CustomErrorConnector>,
+    runtime: Arc<Runtime>,
     nodes: NodeSelector,
     max_num_retries: u32,
     backoff_slot_size: Duration,
+    throttle_switches_node: bool,
     proxy: Option<ProxyState>,
 }
 
@@ -260,6 +262,11 @@ pub struct Client {
     user_agent: HeaderValue,
     tracer: Tracer,
     reload: Option<Reloadable<ServiceDiscoveryConfig>>,
+    // The number of times the client has been rebuilt from a reloaded configuration, which only
+    // tests observe.
+    #[cfg(test)]
+    reloads: AtomicUsize,
+    runtime: Arc<Runtime>,
     state: ArcCell<ClientState>,
 }
 
@@ -269,40 +276,121 @@ impl Client {
         user_agent: UserAgent,
         tracer: &Tracer,
         config: Reloadable<ServiceDiscoveryConfig>,
+    ) -> Result<Client> {
+        Client::new_inner(service, user_agent, tracer, config, RUNTIME.clone())
+    }
+
+    /// Like `new`, but sends requests on a dedicated runtime rather than the shared one.
+    pub fn new_with_runtime(
+        service: &str,
+        user_agent: UserAgent,
+        tracer: &Tracer,
+        config: Reloadable<ServiceDiscoveryConfig>,
+        runtime: &RuntimeConfig,
+    ) -> Result<Client> {
+        let runtime = Arc::new(runtime.build()?);
+        Client::new_inner(service, user_agent, tracer, config, runtime)
+    }
+
+    /// Like `new`, but reads the configuration from a JSON file, which is checked once a second and
+    /// reloaded whenever its contents change.
+    pub fn with_reload<P>(
+        service: &str,
+        user_agent: UserAgent,
+        tracer: &Tracer,
+        path: P,
+    ) -> Result<Client>
+    where
+        P: AsRef<Path>,
+    {
+        Client::with_reload_interval(service, user_agent, tracer, path, Duration::from_secs(1))
+    }
+
+    /// Like `with_reload`, but checks the file every `interval`.
+    pub(crate) fn with_reload_interval<P>(
+        service: &str,
+        user_agent: UserAgent,
+        tracer: &Tracer,
+        path: P,
+        interval: Duration,
+    ) -> Result<Client>
+    where
+        P: AsRef<Path>,
+    {
+        let config = Reloadable::watch_file(path.as_ref(), interval, |contents| {
+            serde_json::from_slice(contents).map_err(Error::internal_safe)
+        })?;
+        Client::new(service, user_agent, tracer, config)
+    }
+
+    pub fn new_static(
+        service: &str,
+        user_agent: UserAgent,
+        tracer: &Tracer,
+        config: &ServiceDiscoveryConfig,
+    ) -> Result<Client> {
+        Client::new_static_inner(service, user_agent, tracer, config, RUNTIME.clone())
+    }
+
+    /// Like `new_static`, but sends requests on a dedicated runtime rather than the shared one.
+    pub fn new_static_with_runtime(
+        service: &str,
+        user_agent: UserAgent,
+        tracer: &Tracer,
+        config: &ServiceDiscoveryConfig,
+        runtime: &RuntimeConfig,
+    ) -> Result<Client> {
+        let runtime = Arc::new(runtime.build()?);
+        Client::new_static_inner(service, user_agent, tracer, config, runtime)
+    }
+
+    fn new_inner(
+        service: &str,
+        user_agent: UserAgent,
+        tracer: &Tracer,
+        config: Reloadable<ServiceDiscoveryConfig>,
+        runtime: Arc<Runtime>,
     ) -> Result<Client> {
         let cur_config = config
             .take()
             .expect("config must be present during client construction");
-        let mut client = Client::new_static(service, user_agent, tracer, &cur_config)?;
+        let mut client =
+            Client::new_static_inner(service, user_agent, tracer, &cur_config, runtime)?;
         client.reload = Some(config);
 
         Ok(client)
     }
 
-    pub fn new_static(
+    fn new_static_inner(
         service: &str,
         mut user_agent: UserAgent,
         tracer: &Tracer,
         config: &ServiceDiscoveryConfig,
+        runtime: Arc<Runtime>,
     ) -> Result<Client> {
         user_agent.push_agent(Agent::new("chatter", env!("CARGO_PKG_VERSION")));
 
-        let state = extract_config(service, config)?;
+        let state = extract_config(service, config, &runtime)?;
 
         Ok(Client {
             service: service.to_string(),
             user_agent: HeaderValue::from_str(&user_agent.to_string()).unwrap(),
             tracer: tracer.clone(),
             reload: None,
+            #[cfg(test)]
+            reloads: AtomicUsize::new(0),
+            runtime,
             state: ArcCell::new(Arc::new(state)),
         })
     }
 
     fn get_refresh(&self) -> Arc<ClientState> {
         match self.reload.as_ref().and_then(|r| r.take()) {
-            Some(config) => match extract_config(&self.service, &config) {
+            Some(config) => match extract_config(&self.service, &config, &self.runtime) {
                 Ok(state) => {
                     info!("reloaded client for service: {}", self.service);
+                    #[cfg(test)]
+                    self.reloads.fetch_add(1, Ordering::SeqCst);
                     let state = Arc::new(state);
                     self.state.set(state.clone());
                     state
@@ -12,12 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use errors::{Error, Result};
+use std::fs;
+use std::path::Path;
 use std::ptr;
 use std::sync::atomic::{AtomicPtr, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 #[derive(Clone)]
-pub struct Reloadable<T>(Arc<AtomicPtr<T>>);
+pub struct Reloadable<T>(Arc<Slot<T>>);
+
+/// Frees the value once nothing refers to it, including a file watcher which set it after the last
+/// `Reloadable` was dropped.
+struct Slot<T>(AtomicPtr<T>);
+
+impl<T> Drop for Slot<T> {
+    fn drop(&mut self) {
+        take(&self.0);
+    }
+}
 
 impl<T> Drop for Reloadable<T> {
     fn drop(&mut self) {
@@ -27,29 +42,93 @@ impl<T> Drop for Reloadable<T> {
 
 impl<T> Reloadable<T> {
     pub fn new(value: T) -> Reloadable<T> {
-        let reloadable = Reloadable(Arc::new(AtomicPtr::new(ptr::null_mut())));
+        let reloadable = Reloadable(Arc::new(Slot(AtomicPtr::new(ptr::null_mut()))));
         reloadable.set(value);
         reloadable
     }
 
     pub fn set(&self, value: T) {
-        unsafe {
-            let value = Box::into_raw(Box::new(value));
-            let old_value = self.0.swap(value, Ordering::SeqCst);
-            if !old_value.is_null() {
-                Box::from_raw(old_value);
-            }
-        }
+        set(&(self.0).0, value);
     }
 
     pub fn take(&self) -> Option<T> {
-        unsafe {
-            let value = self.0.swap(ptr::null_mut(), Ordering::SeqCst);
-            if value.is_null() {
-                None
-            } else {
-                Some(*Box::from_raw(value))
-            }
+        take(&(self.0).0)
+    }
+}
+
+impl<T> Reloadable<T>
+where
+    T: Send + 'static,
+{
+    /// Creates a `Reloadable` holding the value parsed from a file, which is then polled for
+    /// changes every `interval`.
+    ///
+    /// Whenever the file's contents change, they're parsed and set as the new value. Files that
+    /// can't be read or parsed are logged and otherwise ignored. Polling stops once every clone of
+    /// the `Reloadable` has been dropped.
+    pub fn watch_file<F>(path: &Path, interval: Duration, parse: F) -> Result<Reloadable<T>>
+    where
+        F: Fn(&[u8]) -> Result<T> + Send + 'static,
+    {
+        let read = |path: &Path| {
+            fs::read(path).map_err(|e| {
+                Error::internal_safe(e).with_safe_param("path", path.display())
+            })
+        };
+        let mut contents = read(path)?;
+        let reloadable = Reloadable::new(parse(&contents)?);
+
+        let value = Arc::downgrade(&reloadable.0);
+        let path = path.to_path_buf();
+        thread::Builder::new()
+            .name("reloadable-file-watcher".to_string())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                let value = match value.upgrade() {
+                    Some(value) => value,
+                    None => return,
+                };
+                let new_contents = match read(&path) {
+                    Ok(new_contents) => new_contents,
+                    Err(e) => {
+                        error!("error reading reloadable file: {}", e);
+                        continue;
+                    }
+                };
+                if new_contents == contents {
+                    continue;
+                }
+                match parse(&new_contents) {
+                    Ok(new_value) => {
+                        info!("reloaded file: {}", path.display());
+                        set(&value.0, new_value);
+                    }
+                    Err(e) => error!("error parsing reloadable file: {}", e),
+                }
+                contents = new_contents;
+            }).map_err(Error::internal_safe)?;
+
+        Ok(reloadable)
+    }
+}
+
+fn take<T>(ptr: &AtomicPtr<T>) -> Option<T> {
+    unsafe {
+        let value = ptr.swap(ptr::null_mut(), Ordering::SeqCst);
+        if value.is_null() {
+            None
+        } else {
+            Some(*Box::from_raw(value))
+        }
+    }
+}
+
+fn set<T>(ptr: &AtomicPtr<T>, value: T) {
+    unsafe {
+        let value = Box::into_raw(Box::new(value));
+        let old_value = ptr.swap(value, Ordering::SeqCst);
+        if !old_value.is_null() {
+            Box::from_raw(old_value);
         }
     }
 }
@@ -37,7 +37,7 @@ use zipkin::{Endpoint, Kind, TraceContext};
 use async::custom_error::ConnectError;
 use backoff::BackoffIterator;
 use node_selector::Node;
-use {Body, Client, ClientState, IntoBody, ProxyState, Response, RUNTIME};
+use {Body, Client, ClientState, IntoBody, ProxyState, Response};
 
 lazy_static! {
     static ref DEFAULT_ACCEPT: HeaderValue =
@@ -53,6 +53,7 @@ pub struct RequestBuilder<'a> {
     pub(crate) headers: HeaderMap,
     pub(crate) body: Option<Result<Box<Body + 'a>>>,
     pub(crate) idempotent: bool,
+    pub(crate) max_num_retries: Option<u32>,
 }
 
 impl<'a> RequestBuilder<'a> {
@@ -74,6 +75,7 @@ impl<'a> RequestBuilder<'a> {
             headers,
             body: None,
             params: HashMap::new(),
+            max_num_retries: None,
         }
     }
 
@@ -140,6 +142,14 @@ impl<'a> RequestBuilder<'a> {
         self
     }
 
+    /// Sets the maximum number of times this request will be retried.
+    ///
+    /// This overrides the `max-num-retries` of the service's configuration for this request only.
+    pub fn max_retries(&mut self, max_retries: u32) -> &mut RequestBuilder<'a> {
+        self.max_num_retries = Some(max_retries);
+        self
+    }
+
     /// Sets the request body.
     pub fn body<T>(&mut self, body: T) -> &mut RequestBuilder<'a>
     where
@@ -175,7 +185,8 @@ impl<'a> RequestBuilder<'a> {
         };
 
         let mut node = nodes.get();
-        let mut backoffs = BackoffIterator::new(state.max_num_retries, state.backoff_slot_size);
+        let max_num_retries = self.max_num_retries.unwrap_or(state.max_num_retries);
+        let mut backoffs = BackoffIterator::new(max_num_retries, state.backoff_slot_size);
 
         loop {
             let (backoff, reset_body, change_node) =
@@ -191,7 +202,7 @@ impl<'a> RequestBuilder<'a> {
 
                         let backoff = backoff.unwrap_or(internal_backoff);
 
-                        (backoff, true, false)
+                        (backoff, true, state.throttle_switches_node)
                     }
                     Err(SendError::Unavailable) => {
                         let backoff = backoffs.next().ok_or_else(|| {
@@ -373,7 +384,7 @@ impl<'a> RequestBuilder<'a> {
         *request.uri_mut() = url.as_str().parse().unwrap();
         *request.headers_mut() = headers;
 
-        let response = oneshot::spawn(state.client.request(request), &RUNTIME.executor());
+        let response = oneshot::spawn(state.client.request(request), &state.runtime.executor());
 
         if let Some((body, sender)) = body {
             let mut writer = BodyWriter {
@@ -15,6 +15,7 @@
 use errors::{Error, Result};
 use flate2::bufread::{GzDecoder, ZlibDecoder};
 use futures::stream::{self, Stream};
+use hyper::header::{AsHeaderName, HeaderValue};
 use hyper::{self, Body, HeaderMap, StatusCode};
 use mime;
 use serde::de::DeserializeOwned;
@@ -52,10 +53,20 @@ impl Response {
     }
 
     /// Returns the response's headers.
+    ///
+    /// Every value of a repeated header, such as `Set-Cookie`, is preserved.
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
     }
 
+    /// Returns all values of a header, in the order they were received.
+    pub fn header_all<'a, K>(&'a self, name: K) -> impl Iterator<Item = &'a HeaderValue> + 'a
+    where
+        K: AsHeaderName,
+    {
+        self.headers.get_all(name).into_iter()
+    }
+
     fn format(&self) -> Result<Format> {
         let content_type = self
             .headers
@@ -74,6 +85,7 @@ impl Response {
             }
         };
 
+        // raw_body decodes any Content-Encoding, so compressed error bodies can be parsed too
         let body = match self.raw_body() {
             Ok(body) => {
                 let mut buf = vec![];
@@ -0,0 +1,79 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use errors::{Error, Result};
+use std::time::Duration;
+use tokio::runtime::{self, Runtime};
+use tokio_threadpool;
+
+/// Settings for the runtime a `Client` sends its requests on.
+///
+/// Clients share a single runtime with the default settings unless constructed with their own.
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    pool_size: Option<usize>,
+    max_blocking: usize,
+    keep_alive: Option<Duration>,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> RuntimeConfig {
+        RuntimeConfig {
+            pool_size: None,
+            // we use blocking for DNS lookup so we don't need/want a ton of parallelism available
+            max_blocking: 2,
+            keep_alive: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn new() -> RuntimeConfig {
+        RuntimeConfig::default()
+    }
+
+    /// Sets the number of worker threads, which defaults to the number of CPUs.
+    pub fn pool_size(&mut self, pool_size: usize) -> &mut RuntimeConfig {
+        self.pool_size = Some(pool_size);
+        self
+    }
+
+    /// Sets the maximum number of concurrent blocking operations, such as DNS lookups.
+    pub fn max_blocking(&mut self, max_blocking: usize) -> &mut RuntimeConfig {
+        self.max_blocking = max_blocking;
+        self
+    }
+
+    /// Sets how long idle threads are kept around, or `None` to keep them forever.
+    pub fn keep_alive(&mut self, keep_alive: Option<Duration>) -> &mut RuntimeConfig {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub(crate) fn build(&self) -> Result<Runtime> {
+        let mut pool = tokio_threadpool::Builder::new();
+        pool.max_blocking(self.max_blocking)
+            .keep_alive(self.keep_alive)
+            .name_prefix("chatter-");
+        if let Some(pool_size) = self.pool_size {
+            pool.pool_size(pool_size);
+        }
+
+        #[allow(deprecated)]
+        runtime::Builder::new()
+            .threadpool_builder(pool)
+            .build()
+            .map_err(Error::internal_safe)
+    }
+}
@@ -12,20 +12,29 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use futures::future::{self, Future};
-use hyper::header::{HOST, RETRY_AFTER};
+use futures::Stream;
+use hyper::header::{
+    CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, HOST, RETRY_AFTER, SET_COOKIE,
+    TRANSFER_ENCODING,
+};
 use hyper::server::conn::Http;
 use hyper::service::Service;
 use hyper::{self, Body, Request, Response, StatusCode, Version};
 use openssl::ssl::{self, AlpnError, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod};
 use parking_lot::Mutex;
 use serde_json;
-use std::io::Read;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
 use std::net::{SocketAddr, TcpListener};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio::reactor::Handle;
 use tokio::runtime::current_thread::Runtime;
@@ -36,13 +45,13 @@ use config::{
     BasicCredentials, HostAndPort, HttpProxyConfig, ProxyConfig, SecurityConfig, ServiceConfig,
     ServiceDiscoveryConfig,
 };
-use {Agent, Client, UserAgent};
+use {Agent, Client, RawBody, RemoteError, RuntimeConfig, TimeoutKind, UserAgent};
 
 struct TestService<F>(Arc<Mutex<F>>);
 
 impl<F> Service for TestService<F>
 where
-    F: FnMut(Request<Body>) -> Response<Body>,
+    F: FnMut(Request<Body>) -> Response<Body> + 'static + Send,
 {
     type ReqBody = Body;
     type ResBody = Body;
@@ -50,9 +59,14 @@ where
     type Future = Box<Future<Item = Response<Body>, Error = hyper::Error> + Send>;
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let mut f = self.0.lock();
-        let f = &mut *f;
-        Box::new(future::ok(f(req)))
+        // buffer the request body so callbacks can read it without blocking the connection
+        let f = self.0.clone();
+        let (parts, body) = req.into_parts();
+        Box::new(body.concat2().map(move |body| {
+            let mut f = f.lock();
+            let f = &mut *f;
+            f(Request::from_parts(parts, Body::from(body)))
+        }))
     }
 }
 
@@ -311,6 +325,129 @@ fn failover_after_503() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn max_retries_overrides_config() {
+    let server1 = test_server(1, |_| {
+        Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body(Body::empty())
+            .unwrap()
+    });
+    // Nothing listens on the second port, so a retry would fail with an IO error instead
+    let closed_port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": [
+                        "http://localhost:{}",
+                        "http://localhost:{}"
+                    ],
+                    "max-num-retries": 1,
+                    "backoff-slot-size": "1ms"
+                }}
+            }}
+        }}
+        "#,
+        server1.addr.port(),
+        closed_port
+    );
+    let client = client(&config);
+
+    let error = client.get("/").max_retries(0).send().err().unwrap();
+    assert_eq!(
+        error.cause().to_string(),
+        "exceeded max retry limit after 503"
+    );
+}
+
+#[test]
+fn custom_runtime() {
+    let server = test_server(1, |_| Response::new(Body::empty()));
+
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": ["http://localhost:{}"]
+                }}
+            }}
+        }}
+        "#,
+        server.addr.port()
+    );
+    let config = serde_json::from_str(&config).unwrap();
+    let agent = UserAgent::new(Agent::new("test", "1.0"));
+    let tracer = Tracer::builder().build(Endpoint::builder().build());
+    let mut runtime = RuntimeConfig::new();
+    runtime
+        .pool_size(1)
+        .max_blocking(1)
+        .keep_alive(Some(Duration::from_secs(1)));
+    let client =
+        Client::new_static_with_runtime("service", agent, &tracer, &config, &runtime).unwrap();
+
+    let response = client.get("/").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn reload_from_file() {
+    let server1 = test_server(1, |_| Response::new(Body::empty()));
+    let server2 = test_server(2, |_| Response::new(Body::empty()));
+
+    let config = |server: &TestServer| {
+        format!(
+            r#"
+            {{
+                "services": {{
+                    "service": {{
+                        "uris": ["http://localhost:{}"]
+                    }}
+                }}
+            }}
+            "#,
+            server.addr.port()
+        )
+    };
+    let path = env::temp_dir().join(format!("reload-{}.json", server1.addr.port()));
+    fs::write(&path, config(&server1)).unwrap();
+    let agent = UserAgent::new(Agent::new("test", "1.0"));
+    let tracer = Tracer::builder().build(Endpoint::builder().build());
+    let interval = Duration::from_millis(10);
+    let client = Client::with_reload_interval("service", agent, &tracer, &path, interval).unwrap();
+
+    let response = client.get("/").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(client.reloads.load(Ordering::SeqCst), 0);
+
+    // server1 only accepts one request, so the next ones must use the new config.
+    fs::write(&path, config(&server2)).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while client.reloads.load(Ordering::SeqCst) == 0 {
+        assert!(Instant::now() < deadline, "config was never reloaded");
+        thread::sleep(interval);
+        client.get_refresh();
+    }
+    let response = client.get("/").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The file hasn't changed since, so the client isn't rebuilt again.
+    thread::sleep(interval * 10);
+    let response = client.get("/").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(client.reloads.load(Ordering::SeqCst), 1);
+
+    fs::remove_file(&path).unwrap();
+}
+
 #[test]
 fn retry_after_overrides() {
     let mut hit = false;
@@ -348,6 +485,189 @@ fn retry_after_overrides() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[test]
+fn raw_body() {
+    let server = test_server(2, |request| {
+        assert_eq!(
+            request.headers()[CONTENT_TYPE],
+            "application/x-exotic; charset=utf-16be"
+        );
+        if request.headers().contains_key(CONTENT_LENGTH) {
+            assert_eq!(request.headers()[CONTENT_LENGTH], "4");
+        } else {
+            assert_eq!(request.headers()[TRANSFER_ENCODING], "chunked");
+        }
+        let body = request.into_body().concat2().wait().unwrap();
+        assert_eq!(&body[..], b"\x00h\x00i");
+        Response::new(Body::empty())
+    });
+
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": ["http://localhost:{}"]
+                }}
+            }}
+        }}
+        "#,
+        server.addr.port()
+    );
+    let client = client(&config);
+    let body = || {
+        RawBody::new(
+            &b"\x00h\x00i"[..],
+            "application/x-exotic; charset=utf-16be".parse().unwrap(),
+        )
+    };
+
+    let response = client.post("/").body(body()).send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = client
+        .post("/")
+        .body(body().with_content_length(None))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+fn throttling_client(server1: &TestServer, server2: &TestServer, switch: bool) -> Client {
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": [
+                        "http://localhost:{}",
+                        "http://localhost:{}"
+                    ],
+                    "max-num-retries": 1,
+                    "backoff-slot-size": "1ms",
+                    "throttle-switches-node": {}
+                }}
+            }}
+        }}
+        "#,
+        server1.addr.port(),
+        server2.addr.port(),
+        switch
+    );
+    client(&config)
+}
+
+#[test]
+fn repeated_headers() {
+    let server = test_server(1, |_| {
+        Response::builder()
+            .header(SET_COOKIE, "a=1")
+            .header(SET_COOKIE, "b=2")
+            .body(Body::empty())
+            .unwrap()
+    });
+
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": ["http://localhost:{}"]
+                }}
+            }}
+        }}
+        "#,
+        server.addr.port()
+    );
+    let client = client(&config);
+
+    let response = client.get("/").send().unwrap();
+    let cookies = response.header_all(SET_COOKIE).collect::<Vec<_>>();
+    assert_eq!(cookies, vec!["a=1", "b=2"]);
+    assert_eq!(response.headers().get_all(SET_COOKIE).iter().count(), 2);
+    assert_eq!(response.header_all("x-missing").count(), 0);
+}
+
+#[test]
+fn throttle_retries_same_node() {
+    let mut hit = false;
+    let server1 = test_server(2, move |_| {
+        if !hit {
+            hit = true;
+            Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .body(Body::empty())
+                .unwrap()
+        } else {
+            Response::new(Body::empty())
+        }
+    });
+    // server2 isn't listening, so switching to it would exhaust the single retry
+    let server2 = test_server(0, |_| Response::new(Body::empty()));
+    let client = throttling_client(&server1, &server2, false);
+
+    let response = client.get("/").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn throttle_switches_node() {
+    let server1 = test_server(1, |_| {
+        Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .body(Body::empty())
+            .unwrap()
+    });
+    let server2 = test_server(1, |_| Response::new(Body::empty()));
+    let client = throttling_client(&server1, &server2, true);
+
+    let response = client.get("/").send().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn gzip_error_body() {
+    let server = test_server(1, |_| {
+        let error = r#"
+            {
+                "errorCode": "INVALID_ARGUMENT",
+                "errorName": "Test:BadRequest",
+                "errorInstanceId": "1234",
+                "parameters": {}
+            }
+        "#;
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(error.as_bytes()).unwrap();
+        let body = encoder.finish().unwrap();
+
+        Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_ENCODING, "gzip")
+            .body(Body::from(body))
+            .unwrap()
+    });
+
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": ["http://localhost:{}"]
+                }}
+            }}
+        }}
+        "#,
+        server.addr.port()
+    );
+    let client = client(&config);
+
+    let error = client.get("/").send().err().unwrap();
+    let error = error.cause().downcast_ref::<RemoteError>().unwrap();
+    assert_eq!(*error.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(error.error().unwrap().name(), "Test:BadRequest");
+}
+
 #[test]
 fn assume_http2() {
     let server = test_server(1, |request| {
@@ -412,3 +732,100 @@ fn assume_http2_tls() {
     let response = client.get("/").send().unwrap();
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+/// Accepts a single connection, reads whatever arrives within `read_for`, then holds the
+/// connection open without responding for `stall_for`.
+fn stalling_server(read_for: Duration, stall_for: Duration) -> (SocketAddr, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handle = thread::spawn(move || {
+        let mut socket = listener.accept().unwrap().0;
+        if read_for > Duration::from_secs(0) {
+            socket.set_read_timeout(Some(read_for)).unwrap();
+            let _ = socket.read(&mut [0; 4096]);
+        }
+        thread::sleep(stall_for);
+    });
+
+    (addr, handle)
+}
+
+/// Returns a listener that never accepts, along with enough connections to fill its backlog. Once
+/// the backlog is full, further connection attempts hang rather than being refused.
+fn saturated_listener() -> (TcpListener, Vec<std::net::TcpStream>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut connections = vec![];
+    while let Ok(connection) =
+        std::net::TcpStream::connect_timeout(&addr, Duration::from_millis(100))
+    {
+        connections.push(connection);
+        assert!(connections.len() < 4096, "listener backlog never filled up");
+    }
+
+    (listener, connections)
+}
+
+fn timeout_client(uri: &str, timeouts: &str) -> Client {
+    let config = format!(
+        r#"
+        {{
+            "services": {{
+                "service": {{
+                    "uris": ["{}"],
+                    "max-num-retries": 0,
+                    {}
+                }}
+            }}
+        }}
+        "#,
+        uri, timeouts
+    );
+    client(&config)
+}
+
+#[test]
+fn connect_timeout() {
+    let (listener, _connections) = saturated_listener();
+    let client = timeout_client(
+        &format!("http://{}", listener.local_addr().unwrap()),
+        r#""connect-timeout": "100ms""#,
+    );
+
+    let error = client.get("/").send().err().unwrap();
+    assert_eq!(TimeoutKind::of(error.cause()), Some(TimeoutKind::Connect));
+}
+
+#[test]
+fn read_timeout() {
+    let (addr, handle) = stalling_server(Duration::from_millis(100), Duration::from_secs(1));
+    let client = timeout_client(
+        &format!("http://localhost:{}", addr.port()),
+        r#""read-timeout": "100ms", "write-timeout": "10s""#,
+    );
+
+    let error = client.get("/").send().err().unwrap();
+    assert_eq!(TimeoutKind::of(error.cause()), Some(TimeoutKind::Read));
+    handle.join().unwrap();
+}
+
+#[test]
+fn write_timeout() {
+    // the server never reads, so the client blocks once the socket buffers fill up. Linux caps
+    // those at 4MB for sending and 6MB for receiving by default, so 16MB is comfortably more.
+    let (addr, handle) = stalling_server(Duration::from_secs(0), Duration::from_secs(1));
+    let client = timeout_client(
+        &format!("http://localhost:{}", addr.port()),
+        r#""read-timeout": "10s", "write-timeout": "100ms""#,
+    );
+    let body = RawBody::new(
+        vec![0; 16 * 1024 * 1024],
+        "application/octet-stream".parse().unwrap(),
+    );
+
+    let error = client.post("/").body(body).send().err().unwrap();
+    assert_eq!(TimeoutKind::of(error.cause()), Some(TimeoutKind::Write));
+    handle.join().unwrap();
+}
@@ -0,0 +1,189 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Response compression, for verifying that clients decode compressed responses correctly.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use hyper::header::HeaderValue;
+use hyper::HeaderMap;
+use response::{Body, Response};
+use router::Middleware;
+use std::io;
+use std::io::Write;
+
+/// Gzips fixed-size response bodies if the request's `Accept-Encoding` header allows it.
+///
+/// Every other coding is unsupported, so requests that only accept those, e.g. `br`, get an
+/// uncompressed response rather than an error. Streaming bodies are never compressed.
+pub struct GzipMiddleware;
+
+/// Whether the `Accept-Encoding` headers give `gzip`, or failing that `*`, a nonzero quality.
+///
+/// Malformed values are ignored, and a malformed quality counts as zero.
+fn accepts_gzip(request_headers: &HeaderMap) -> bool {
+    let mut gzip = None;
+    let mut wildcard = None;
+    for value in request_headers.get_all(ACCEPT_ENCODING) {
+        let value = match value.to_str() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        for item in value.split(',') {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap_or("").trim();
+            let quality = params
+                .map(|param| param.trim())
+                .find(|param| param.starts_with("q=") || param.starts_with("Q="))
+                .map_or(1.0, |param| param[2..].parse().unwrap_or(0.0));
+            if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+                gzip = Some(quality);
+            } else if coding == "*" {
+                wildcard = Some(quality);
+            }
+        }
+    }
+    gzip.or(wildcard).map_or(false, |quality: f32| quality > 0.0)
+}
+
+fn gzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+impl Middleware for GzipMiddleware {
+    fn on_response(&self, request_headers: &HeaderMap, response: &mut Response) {
+        if response.headers.contains_key(CONTENT_ENCODING) {
+            return;
+        }
+        let compressed = match response.body {
+            Body::Fixed(ref bytes) => {
+                response
+                    .headers
+                    .append(VARY, HeaderValue::from_static("Accept-Encoding"));
+                if !accepts_gzip(request_headers) {
+                    return;
+                }
+                match gzip(bytes) {
+                    Ok(compressed) => compressed,
+                    Err(e) => {
+                        warn!("failed to gzip response body, sending it uncompressed: {}", e);
+                        return;
+                    }
+                }
+            }
+            Body::Empty | Body::Streaming(_) => return,
+        };
+
+        response
+            .headers
+            .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        response
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+        response.body = Body::Fixed(compressed.into());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure_verification_error::Result;
+    use flate2::read::GzDecoder;
+    use futures::{Future, Stream};
+    use handler::HttpService;
+    use http::header::CONTENT_TYPE;
+    use hyper;
+    use hyper::service::Service;
+    use hyper::Method;
+    use hyper::StatusCode;
+    use request::Request;
+    use router::{Endpoint, Handle, Router};
+    use std::io::Read;
+    use std::sync::Arc;
+
+    struct BodyHandler;
+
+    impl Handle for BodyHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            let mut response = Response::new(StatusCode::OK);
+            response
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            response
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from_static("2"));
+            response.body = Body::Fixed("{}".into());
+            Ok(response)
+        }
+    }
+
+    fn get(accept_encoding: Option<&str>) -> (hyper::Response<()>, Vec<u8>) {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/body".to_string(),
+            Endpoint {
+                handler: Box::new(BodyHandler),
+            },
+        );
+        builder.middleware(GzipMiddleware);
+        let mut request = hyper::Request::get("/body");
+        if let Some(accept_encoding) = accept_encoding {
+            request.header(ACCEPT_ENCODING, accept_encoding);
+        }
+        let response = HttpService::new(Arc::new(builder.build()))
+            .call(request.body(hyper::Body::empty()).unwrap())
+            .wait()
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        let body = body.concat2().wait().unwrap().to_vec();
+        (hyper::Response::from_parts(parts, ()), body)
+    }
+
+    #[test]
+    fn gzips_when_accepted() {
+        for accept_encoding in &["gzip", "br, gzip;q=0.5", "*", "GZIP;Q=1"] {
+            let (response, body) = get(Some(*accept_encoding));
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+            assert_eq!(response.headers()[CONTENT_LENGTH], body.len().to_string().as_str());
+            let mut decoded = vec![];
+            GzDecoder::new(&body[..]).read_to_end(&mut decoded).unwrap();
+            assert_eq!(decoded, b"{}");
+        }
+    }
+
+    #[test]
+    fn falls_back_to_identity_for_unsupported_encodings() {
+        let accept_encodings = [
+            None,
+            Some("br"),
+            Some("br, identity;q=0"),
+            Some("gzip;q=0, br"),
+            Some("*, gzip;q=0"),
+            Some("gzip;q=bogus"),
+        ];
+        for accept_encoding in &accept_encodings {
+            let (response, body) = get(*accept_encoding);
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!response.headers().contains_key(CONTENT_ENCODING));
+            assert_eq!(response.headers()[CONTENT_LENGTH], "2");
+            assert_eq!(response.headers()[VARY], "Accept-Encoding");
+            assert_eq!(body, b"{}");
+        }
+    }
+}
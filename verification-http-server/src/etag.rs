@@ -0,0 +1,166 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conditional request support, for verifying that clients cache responses correctly.
+
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use hyper::header::HeaderValue;
+use hyper::HeaderMap;
+use hyper::StatusCode;
+use response::{Body, Response};
+use router::Middleware;
+
+/// Tags every successful response with a fixed `ETag`, and replaces it with `304 Not Modified` if
+/// the request's `If-None-Match` header matches that tag.
+pub struct ETagMiddleware {
+    etag: HeaderValue,
+}
+
+impl ETagMiddleware {
+    /// Creates a middleware using the given opaque tag, which is quoted as the HTTP spec requires.
+    ///
+    /// Fails unless the tag is made of visible ASCII characters other than `"`.
+    pub fn new(tag: &str) -> Result<ETagMiddleware, String> {
+        if tag.bytes().any(|b| b == b'"' || b < 0x21 || b > 0x7e) {
+            return Err(format!(
+                "Entity tags must be visible ASCII characters other than '\"', got '{}'",
+                tag
+            ));
+        }
+        let etag = HeaderValue::from_str(&format!("\"{}\"", tag))
+            .map_err(|_| format!("Invalid entity tag '{}'", tag))?;
+        Ok(ETagMiddleware { etag })
+    }
+
+    /// Whether the `If-None-Match` header names our tag, using the weak comparison function.
+    fn matches(&self, if_none_match: &HeaderValue) -> bool {
+        let if_none_match = match if_none_match.to_str() {
+            Ok(if_none_match) => if_none_match,
+            Err(_) => return false,
+        };
+        if if_none_match.trim() == "*" {
+            return true;
+        }
+        if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_left_matches("W/"))
+            .any(|tag| self.etag == *tag)
+    }
+}
+
+impl Middleware for ETagMiddleware {
+    fn on_response(&self, request_headers: &HeaderMap, response: &mut Response) {
+        if !response.status.is_success() {
+            return;
+        }
+
+        response.headers.insert(ETAG, self.etag.clone());
+        if request_headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .any(|value| self.matches(value))
+        {
+            response.status = StatusCode::NOT_MODIFIED;
+            response.body = Body::Empty;
+            response.headers.remove(CONTENT_LENGTH);
+            response.headers.remove(CONTENT_TYPE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure_verification_error::Result;
+    use futures::{Future, Stream};
+    use handler::HttpService;
+    use hyper;
+    use hyper::service::Service;
+    use hyper::Method;
+    use request::Request;
+    use router::{Endpoint, Handle, Router};
+    use std::sync::Arc;
+
+    struct BodyHandler;
+
+    impl Handle for BodyHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            let mut response = Response::new(StatusCode::OK);
+            response
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            response
+                .headers
+                .insert(CONTENT_LENGTH, HeaderValue::from_static("2"));
+            response.body = Body::Fixed("{}".into());
+            Ok(response)
+        }
+    }
+
+    fn service() -> HttpService {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/body".to_string(),
+            Endpoint {
+                handler: Box::new(BodyHandler),
+            },
+        );
+        builder.middleware(ETagMiddleware::new("v1").unwrap());
+        HttpService::new(Arc::new(builder.build()))
+    }
+
+    fn get(if_none_match: Option<&str>) -> hyper::Response<hyper::Body> {
+        let mut request = hyper::Request::get("/body");
+        if let Some(if_none_match) = if_none_match {
+            request.header(IF_NONE_MATCH, if_none_match);
+        }
+        service()
+            .call(request.body(hyper::Body::empty()).unwrap())
+            .wait()
+            .unwrap()
+    }
+
+    #[test]
+    fn initial_response_has_etag() {
+        let response = get(None);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[ETAG], "\"v1\"");
+        let body = response.into_body().concat2().wait().unwrap();
+        assert_eq!(&body[..], b"{}");
+
+        let response = get(Some("\"v0\""));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[ETAG], "\"v1\"");
+    }
+
+    #[test]
+    fn matching_if_none_match_is_not_modified() {
+        for if_none_match in &["\"v1\"", "W/\"v1\"", "\"v0\", \"v1\"", "*"] {
+            let response = get(Some(if_none_match));
+            assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+            assert_eq!(response.headers()[ETAG], "\"v1\"");
+            assert!(!response.headers().contains_key(CONTENT_LENGTH));
+            let body = response.into_body().concat2().wait().unwrap();
+            assert!(body.is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_tags_that_cannot_be_sent() {
+        for tag in &["a\"b", "a b", "a\u{7f}b", "caf\u{e9}"] {
+            assert!(ETagMiddleware::new(tag).is_err(), "{:?}", tag);
+        }
+    }
+}
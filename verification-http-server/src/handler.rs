@@ -31,12 +31,16 @@ use futures::Poll;
 use futures::Stream;
 use hyper::body;
 use hyper::service::Service;
+use hyper::header::{HeaderValue, CONTENT_LENGTH, RETRY_AFTER, TRANSFER_ENCODING};
 use hyper::{self, Chunk, HeaderMap, StatusCode, Uri};
+use idempotency::{IdempotencyCache, IdempotencyKey};
 use itertools::Itertools;
 use log::Level;
+use rate_limit::RateLimiter;
 use router::Endpoint;
 use router::RouteResult;
 use router::Router;
+use std::any::Any;
 use std::collections::HashMap;
 use std::error::Error as StdError;
 use std::io;
@@ -45,27 +49,93 @@ use std::io::BufReader;
 use std::io::Cursor;
 use std::io::Read;
 use std::io::Write;
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::executor::thread_pool::ThreadPool;
-use typed_headers::{Allow, ContentCoding, ContentEncoding, HeaderMapExt};
+use tokio::timer::Timeout;
+use typed_headers::{Allow, ContentCoding, ContentEncoding, ContentLength, HeaderMapExt};
 use url::{form_urlencoded, percent_encoding};
 
 pub struct HttpService {
     router: Arc<Router>,
     sync: Arc<SyncHandler>,
     pool: Arc<ThreadPool>,
+    peer_addr: Option<SocketAddr>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    handler_timeout: Option<Duration>,
+    idempotency_cache: Option<Arc<IdempotencyCache>>,
 }
 
 impl HttpService {
     pub fn new(router: Arc<Router>) -> HttpService {
         HttpService {
+            sync: Arc::new(SyncHandler {
+                router: router.clone(),
+                allow_chunked_content_length: false,
+            }),
             router,
-            sync: Arc::new(SyncHandler),
             pool: Arc::new(ThreadPool::new()),
+            peer_addr: None,
+            rate_limiter: None,
+            handler_timeout: None,
+            idempotency_cache: None,
         }
     }
 
+    /// Sets the address of the client at the other end of the connection this service handles.
+    pub fn with_peer_addr(mut self, peer_addr: SocketAddr) -> HttpService {
+        self.peer_addr = Some(peer_addr);
+        self
+    }
+
+    /// Rejects requests with 429 once the peer exceeds the rate limit.
+    ///
+    /// Has no effect unless the peer address is known.
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> HttpService {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Responds with 503 if a handler takes longer than `handler_timeout` to produce a response.
+    ///
+    /// The `Retry-After` header asks clients to wait for the length of the timeout before retrying.
+    pub fn with_handler_timeout(mut self, handler_timeout: Duration) -> HttpService {
+        self.handler_timeout = Some(handler_timeout);
+        self
+    }
+
+    /// Replays the cached response to requests repeating the `Idempotency-Key` of an earlier one.
+    pub fn with_idempotency_cache(
+        mut self,
+        idempotency_cache: Arc<IdempotencyCache>,
+    ) -> HttpService {
+        self.idempotency_cache = Some(idempotency_cache);
+        self
+    }
+
+    /// Accepts requests with both `Transfer-Encoding: chunked` and `Content-Length`, ignoring the
+    /// latter as RFC 7230 permits, rather than rejecting them with 400.
+    pub fn with_chunked_content_length_allowed(mut self, allowed: bool) -> HttpService {
+        self.sync = Arc::new(SyncHandler {
+            router: self.router.clone(),
+            allow_chunked_content_length: allowed,
+        });
+        self
+    }
+
+    fn rate_limit(&self) -> Option<hyper::Response<hyper::Body>> {
+        let (rate_limiter, peer_addr) = match (&self.rate_limiter, self.peer_addr) {
+            (Some(rate_limiter), Some(peer_addr)) => (rate_limiter, peer_addr),
+            _ => return None,
+        };
+        let retry_after = rate_limiter.acquire(peer_addr.ip()).err()?;
+        info!("rate limited request from {}", peer_addr.ip());
+        Some(retry_after_response(StatusCode::TOO_MANY_REQUESTS, retry_after))
+    }
+
     fn route(&self, request: &hyper::Request<hyper::Body>) -> RouteResult {
         let path = &request.uri().path();
         self.router.route(request.method(), path)
@@ -134,15 +204,22 @@ impl HttpService {
                 *response.status_mut() = StatusCode::NOT_FOUND;
                 Box::new(future::ok((response, 0)))
             }
-            (RouteResult::Matched { endpoint, .. }, Ok(path_params)) => {
+            (RouteResult::Matched { endpoint, params, .. }, Ok(path_params)) => {
+                let raw_path_params = params
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect();
                 let (sender, receiver) = oneshot::channel();
 
                 let sync = self.sync.clone();
+                let peer_addr = self.peer_addr;
                 let r = self.pool.sender().spawn(future::lazy(move || {
                     sync.response(
                         request,
+                        peer_addr,
                         endpoint,
                         path_params,
+                        raw_path_params,
                         query_params,
                         sender,
                         &response_size,
@@ -150,17 +227,30 @@ impl HttpService {
                     Ok(())
                 }));
 
-                match r {
-                    Ok(()) => {
-                        let f = receiver.or_else(|_| {
-                            error!("handler thread hung up");
-                            let mut response = hyper::Response::new(hyper::Body::empty());
-                            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
-                            Box::new(future::ok((response, 0)))
+                match (r, self.handler_timeout) {
+                    (Ok(()), None) => {
+                        let f = receiver.or_else(|_| Ok(handler_hung_up()));
+                        Box::new(f)
+                    }
+                    (Ok(()), Some(timeout)) => {
+                        let f = Timeout::new(receiver, timeout).or_else(move |e| {
+                            if e.is_elapsed() {
+                                info!("handler timed out after {:?}", timeout);
+                                let response =
+                                    retry_after_response(StatusCode::SERVICE_UNAVAILABLE, timeout);
+                                Ok((response, 0))
+                            } else if e.is_inner() {
+                                Ok(handler_hung_up())
+                            } else {
+                                error!("handler timeout failed: {}", e);
+                                let mut response = hyper::Response::new(hyper::Body::empty());
+                                *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                                Ok((response, 0))
+                            }
                         });
                         Box::new(f)
                     }
-                    Err(_) => {
+                    (Err(_), _) => {
                         let mut response = hyper::Response::new(hyper::Body::empty());
                         *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
                         Box::new(future::ok((response, 0)))
@@ -171,14 +261,62 @@ impl HttpService {
     }
 }
 
-struct SyncHandler;
+fn handler_hung_up() -> (hyper::Response<hyper::Body>, u64) {
+    error!("handler thread hung up");
+    let mut response = hyper::Response::new(hyper::Body::empty());
+    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+    (response, 0)
+}
+
+/// An empty response asking the client to retry after the given delay.
+fn retry_after_response(status: StatusCode, retry_after: Duration) -> hyper::Response<hyper::Body> {
+    // Retry-After is in whole seconds, so round up.
+    let mut retry_after_secs = retry_after.as_secs();
+    if retry_after.subsec_nanos() > 0 || retry_after_secs == 0 {
+        retry_after_secs += 1;
+    }
+    let mut response = hyper::Response::new(hyper::Body::empty());
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+    response
+}
+
+struct SyncHandler {
+    router: Arc<Router>,
+    allow_chunked_content_length: bool,
+}
+
+/// Whether the request's body is sent with the chunked transfer coding.
+fn is_chunked(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(TRANSFER_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+}
+
+fn panic_error(what: &str, payload: &(Any + Send)) -> Error {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<unknown>".to_string()
+    };
+    Error::internal_safe(format!("{} panicked", what)).with_unsafe_param("panic", message)
+}
 
 impl SyncHandler {
     fn response(
         &self,
         request: hyper::Request<hyper::Body>,
+        peer_addr: Option<SocketAddr>,
         endpoint: Arc<Endpoint>,
         path_params: HashMap<String, String>,
+        raw_path_params: HashMap<String, String>,
         query_params: HashMap<String, Vec<String>>,
         sender: oneshot::Sender<(hyper::Response<hyper::Body>, u64)>,
         response_size: &Arc<AtomicUsize>,
@@ -191,19 +329,39 @@ impl SyncHandler {
         };
         let mut body = SizeTrackingReader {
             reader: body,
-            size: 0,
+            size: Arc::new(AtomicUsize::new(0)),
+            // A chunked body's length is determined by its chunks, not by Content-Length.
+            declared_size: if is_chunked(&parts.headers) {
+                None
+            } else {
+                parts
+                    .headers
+                    .typed_get::<ContentLength>()
+                    .ok()
+                    .and_then(|content_length| content_length)
+                    .map(|content_length| content_length.0)
+            },
         };
 
-        let response = self
-            .response_inner(
+        // A panicking handler would otherwise hang up the oneshot, so convert it to a regular error.
+        let response = match panic::catch_unwind(AssertUnwindSafe(|| {
+            self.response_inner(
                 &parts.headers,
+                peer_addr,
                 &mut body,
                 &endpoint,
                 &path_params,
+                &raw_path_params,
                 &query_params,
-            ).unwrap_or_else(|e| self.handler_error(&e));
+            )
+        })) {
+            Ok(response) => response,
+            Err(payload) => Err(panic_error("handler", &*payload)),
+        };
+        let response = response.unwrap_or_else(|e| self.handler_error(&e));
 
-        self.write_response(&parts.headers, response, body.size, sender, &response_size);
+        let request_size = body.size.load(Ordering::SeqCst) as u64;
+        self.write_response(&parts.headers, response, request_size, sender, &response_size);
     }
 
     fn handler_error(&self, e: &Error) -> Response {
@@ -219,13 +377,32 @@ impl SyncHandler {
     fn response_inner(
         &self,
         headers: &HeaderMap,
+        peer_addr: Option<SocketAddr>,
         body: &mut SizeTrackingReader<BodyReader>,
         endpoint: &Arc<Endpoint>,
         path_params: &HashMap<String, String>,
+        raw_path_params: &HashMap<String, String>,
         query_params: &HashMap<String, Vec<String>>,
     ) -> Result<Response> {
+        // hyper's parser already drops a Content-Length that follows Transfer-Encoding: chunked,
+        // so this only sees the headers the other way around.
+        if !self.allow_chunked_content_length
+            && is_chunked(headers)
+            && headers.contains_key(CONTENT_LENGTH)
+        {
+            return Err(Error::new_safe(
+                "request has both Transfer-Encoding: chunked and Content-Length",
+                ConjureVerificationError::ConflictingContentLength,
+            ));
+        }
+        let body_bytes_read = body.size.clone();
         let mut body = self.decode_body(&headers, body)?;
-        let mut request = Request::new(&path_params, &query_params, &headers, &mut *body);
+        let mut request = Request::new(&path_params, &query_params, &headers, &mut *body)
+            .with_body_bytes_read(body_bytes_read)
+            .with_raw_path_params(raw_path_params);
+        if let Some(peer_addr) = peer_addr {
+            request = request.with_peer_addr(peer_addr);
+        }
 
         endpoint.handler.handle(&mut request)
     }
@@ -259,11 +436,20 @@ impl SyncHandler {
     fn write_response(
         &self,
         headers: &HeaderMap,
-        raw_response: Response,
+        mut raw_response: Response,
         request_size: u64,
         sender: oneshot::Sender<(hyper::Response<hyper::Body>, u64)>,
         response_size: &Arc<AtomicUsize>,
     ) {
+        // Like handlers, a panicking middleware would otherwise hang up the oneshot.
+        let applied = panic::catch_unwind(AssertUnwindSafe(|| {
+            for middleware in self.router.middleware() {
+                middleware.on_response(headers, &mut raw_response);
+            }
+        }));
+        if let Err(payload) = applied {
+            raw_response = self.handler_error(&panic_error("middleware", &*payload));
+        }
         let raw_response = self.handle_response_size(response_size, raw_response);
         // TODO(dsanduleac): don't wanna encode
         //        let raw_response = encode::encode(headers, raw_response);
@@ -364,6 +550,20 @@ impl Service for HttpService {
         request: hyper::Request<<Self as Service>::ReqBody>,
     ) -> Box<Future<Item = hyper::Response<hyper::Body>, Error = Box<StdError + Sync + Send>> + Send>
     {
+        if let Some(response) = self.rate_limit() {
+            return Box::new(future::ok(response));
+        }
+
+        let idempotency = match (&self.idempotency_cache, IdempotencyKey::of(&request)) {
+            (Some(cache), Some(key)) => {
+                if let Some(response) = cache.get(&key) {
+                    return Box::new(future::ok(response));
+                }
+                Some((cache.clone(), key))
+            }
+            _ => None,
+        };
+
         let route = self.route(&request);
         let query_params = self.query_params(request.uri());
         let maybe_path_params = self.path_params(&route);
@@ -378,7 +578,18 @@ impl Service for HttpService {
                 response_size,
             ).map({ move |(response, _request_size)| response });
 
-        Box::new(f)
+        match idempotency {
+            Some((cache, key)) => Box::new(f.and_then(move |response| {
+                // Buffer the body so that it can be both cached and sent.
+                let (parts, body) = response.into_parts();
+                body.concat2().from_err().map(move |body| {
+                    let body = body.into_bytes();
+                    cache.insert(key, parts.status, &parts.headers, body.clone());
+                    hyper::Response::from_parts(parts, hyper::Body::from(body))
+                })
+            })),
+            None => Box::new(f),
+        }
     }
 }
 
@@ -418,9 +629,23 @@ impl BufRead for BodyReader {
     }
 }
 
+/// Counts the bytes read, and fails reads that go past the declared `Content-Length`, if any.
 struct SizeTrackingReader<R> {
     reader: R,
-    size: u64,
+    size: Arc<AtomicUsize>,
+    declared_size: Option<u64>,
+}
+
+impl<R> SizeTrackingReader<R> {
+    fn check_declared_size(&self, size: u64) -> io::Result<()> {
+        match self.declared_size {
+            Some(declared_size) if size > declared_size => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "request body is longer than its Content-Length",
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<R> Read for SizeTrackingReader<R>
@@ -428,10 +653,10 @@ where
     R: Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.reader.read(buf).map(|n| {
-            self.size += n as u64;
-            n
-        })
+        let n = self.reader.read(buf)?;
+        let size = self.size.fetch_add(n, Ordering::SeqCst) + n;
+        self.check_declared_size(size as u64)?;
+        Ok(n)
     }
 }
 
@@ -440,11 +665,13 @@ where
     R: BufRead,
 {
     fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let size = self.size.load(Ordering::SeqCst) + self.reader.fill_buf()?.len();
+        self.check_declared_size(size as u64)?;
         self.reader.fill_buf()
     }
 
     fn consume(&mut self, amt: usize) {
-        self.size += amt as u64;
+        self.size.fetch_add(amt, Ordering::SeqCst);
         self.reader.consume(amt)
     }
 }
@@ -580,3 +807,490 @@ impl<'a> Future for BodySendFuture<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure_verification_error::SerializableError;
+    use hyper::Method;
+    use hyper::header::{HeaderName, HeaderValue};
+    use hyper::server::conn::AddrStream;
+    use hyper::service::make_service_fn;
+    use router::Handle;
+    use router::Middleware;
+    use serde_json;
+    use std::net::TcpStream;
+    use std::thread;
+    use tokio::runtime::Runtime;
+
+    struct OkHandler;
+
+    impl Handle for OkHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            Ok(Response::new(StatusCode::OK))
+        }
+    }
+
+    struct FailingHandler;
+
+    impl Handle for FailingHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            Err(Error::new_safe("bad request", Code::InvalidArgument))
+        }
+    }
+
+    struct HeaderMiddleware(&'static str);
+
+    impl Middleware for HeaderMiddleware {
+        fn on_response(&self, request_headers: &HeaderMap, response: &mut Response) {
+            let value = request_headers
+                .get("Request-Id")
+                .cloned()
+                .unwrap_or_else(|| HeaderValue::from_static("none"));
+            response
+                .headers
+                .append(HeaderName::from_static(self.0), value);
+        }
+    }
+
+    struct BodyBytesReadHandler;
+
+    impl Handle for BodyBytesReadHandler {
+        fn handle(&self, request: &mut Request) -> Result<Response> {
+            let mut body = vec![];
+            request.raw_body().read_to_end(&mut body).unwrap();
+            let mut response = Response::new(StatusCode::OK);
+            response.headers.insert(
+                "x-body-bytes-read",
+                HeaderValue::from(request.body_bytes_read().unwrap()),
+            );
+            Ok(response)
+        }
+    }
+
+    struct PanickingMiddleware;
+
+    impl Middleware for PanickingMiddleware {
+        fn on_response(&self, request_headers: &HeaderMap, _: &mut Response) {
+            if request_headers.contains_key("Panic") {
+                panic!("middleware panicked");
+            }
+        }
+    }
+
+    struct ReadBodyHandler;
+
+    impl Handle for ReadBodyHandler {
+        fn handle(&self, request: &mut Request) -> Result<Response> {
+            let mut body = vec![];
+            request
+                .raw_body()
+                .read_to_end(&mut body)
+                .map_err(|e| Error::new_safe(e, Code::CustomClient))?;
+            Ok(Response::new(StatusCode::OK))
+        }
+    }
+
+    struct CountingHandler(AtomicUsize);
+
+    impl Handle for CountingHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            let mut response = Response::new(StatusCode::OK);
+            response.body = Body::Fixed(count.to_string().into());
+            Ok(response)
+        }
+    }
+
+    struct SlowHandler;
+
+    impl Handle for SlowHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            thread::sleep(Duration::from_millis(500));
+            Ok(Response::new(StatusCode::OK))
+        }
+    }
+
+    struct PeerAddrHandler;
+
+    impl Handle for PeerAddrHandler {
+        fn handle(&self, request: &mut Request) -> Result<Response> {
+            let peer_addr = match request.peer_addr() {
+                Some(peer_addr) => peer_addr.to_string(),
+                None => "unknown".to_string(),
+            };
+            let mut response = Response::new(StatusCode::OK);
+            response
+                .headers
+                .insert("x-peer-addr", HeaderValue::from_str(&peer_addr).unwrap());
+            Ok(response)
+        }
+    }
+
+    struct PanickingHandler;
+
+    impl Handle for PanickingHandler {
+        fn handle(&self, _: &mut Request) -> Result<Response> {
+            panic!("bad test case")
+        }
+    }
+
+    #[test]
+    fn panicking_handler_returns_internal_error() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/panic".to_string(),
+            Endpoint {
+                handler: Box::new(PanickingHandler),
+            },
+        );
+        let mut service = HttpService::new(Arc::new(builder.build()));
+
+        let request = hyper::Request::get("/panic")
+            .body(hyper::Body::empty())
+            .unwrap();
+        let response = service.call(request).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: SerializableError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code(), "INTERNAL");
+        assert_eq!(error.name(), "Default:Internal");
+    }
+
+    #[test]
+    fn middleware_applies_to_all_responses() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/ok".to_string(),
+            Endpoint {
+                handler: Box::new(OkHandler),
+            },
+        );
+        builder.route(
+            Method::GET,
+            "/fail".to_string(),
+            Endpoint {
+                handler: Box::new(FailingHandler),
+            },
+        );
+        builder.route(
+            Method::GET,
+            "/panic".to_string(),
+            Endpoint {
+                handler: Box::new(PanickingHandler),
+            },
+        );
+        builder
+            .middleware(HeaderMiddleware("x-first"))
+            .middleware(HeaderMiddleware("x-second"))
+            .middleware(PanickingMiddleware);
+        let mut service = HttpService::new(Arc::new(builder.build()));
+
+        for (path, status) in &[
+            ("/ok", StatusCode::OK),
+            ("/fail", StatusCode::BAD_REQUEST),
+            ("/panic", StatusCode::INTERNAL_SERVER_ERROR),
+        ] {
+            let request = hyper::Request::get(*path)
+                .header("Request-Id", "abc")
+                .body(hyper::Body::empty())
+                .unwrap();
+            let response = service.call(request).wait().unwrap();
+            assert_eq!(response.status(), *status);
+            assert_eq!(response.headers()["x-first"], "abc");
+            assert_eq!(response.headers()["x-second"], "abc");
+        }
+
+        // A panicking middleware still produces a Conjure error rather than hanging up.
+        let request = hyper::Request::get("/ok")
+            .header("Panic", "true")
+            .body(hyper::Body::empty())
+            .unwrap();
+        let response = service.call(request).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: SerializableError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code(), "INTERNAL");
+    }
+
+    #[test]
+    fn rate_limits_peers() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/ok".to_string(),
+            Endpoint {
+                handler: Box::new(OkHandler),
+            },
+        );
+        let router = Arc::new(builder.build());
+        let rate_limiter = Arc::new(RateLimiter::new(2, Duration::from_millis(200)));
+        let service = |peer_addr: &str| {
+            HttpService::new(router.clone())
+                .with_peer_addr(peer_addr.parse().unwrap())
+                .with_rate_limiter(rate_limiter.clone())
+        };
+        let get = |service: &mut HttpService| {
+            let request = hyper::Request::get("/ok")
+                .body(hyper::Body::empty())
+                .unwrap();
+            service.call(request).wait().unwrap()
+        };
+
+        // Each connection gets its own service, but they share the limit for the same IP.
+        for port in &[1000, 1001] {
+            let response = get(&mut service(&format!("10.0.0.1:{}", port)));
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = get(&mut service("10.0.0.1:1002"));
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+
+        let response = get(&mut service("10.0.0.2:1000"));
+        assert_eq!(response.status(), StatusCode::OK);
+
+        thread::sleep(Duration::from_millis(200));
+        let response = get(&mut service("10.0.0.1:1003"));
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn handler_timeout_returns_unavailable() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/ok".to_string(),
+            Endpoint {
+                handler: Box::new(OkHandler),
+            },
+        );
+        builder.route(
+            Method::GET,
+            "/slow".to_string(),
+            Endpoint {
+                handler: Box::new(SlowHandler),
+            },
+        );
+        let mut service = HttpService::new(Arc::new(builder.build()))
+            .with_handler_timeout(Duration::from_millis(100));
+        // The timeout needs a timer, which the runtime provides.
+        let mut runtime = Runtime::new().unwrap();
+        let mut get = |path| {
+            let request = hyper::Request::get(path)
+                .body(hyper::Body::empty())
+                .unwrap();
+            runtime.block_on(service.call(request)).unwrap()
+        };
+
+        let response = get("/ok");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key(RETRY_AFTER));
+
+        let response = get("/slow");
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+    }
+
+    #[test]
+    fn exposes_peer_addr() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::GET,
+            "/peer".to_string(),
+            Endpoint {
+                handler: Box::new(PeerAddrHandler),
+            },
+        );
+        let router = Arc::new(builder.build());
+        let get = |mut service: HttpService| {
+            let request = hyper::Request::get("/peer")
+                .body(hyper::Body::empty())
+                .unwrap();
+            service.call(request).wait().unwrap()
+        };
+
+        let response = get(HttpService::new(router.clone()));
+        assert_eq!(response.headers()["x-peer-addr"], "unknown");
+
+        let service = HttpService::new(router).with_peer_addr("10.0.0.1:1234".parse().unwrap());
+        let response = get(service);
+        assert_eq!(response.headers()["x-peer-addr"], "10.0.0.1:1234");
+    }
+
+    #[test]
+    fn tracks_body_bytes_read() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::POST,
+            "/body".to_string(),
+            Endpoint {
+                handler: Box::new(BodyBytesReadHandler),
+            },
+        );
+        let mut service = HttpService::new(Arc::new(builder.build()));
+
+        let body = "a streaming body, delivered in chunks";
+        let chunks = body
+            .as_bytes()
+            .chunks(8)
+            .map(|chunk| Ok::<_, hyper::Error>(chunk.to_vec()))
+            .collect::<Vec<_>>();
+        let request = hyper::Request::post("/body")
+            .body(hyper::Body::wrap_stream(stream::iter_result(chunks)))
+            .unwrap();
+        let response = service.call(request).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["x-body-bytes-read"],
+            body.len().to_string().as_str()
+        );
+    }
+
+    #[test]
+    fn rejects_body_longer_than_content_length() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::POST,
+            "/body".to_string(),
+            Endpoint {
+                handler: Box::new(ReadBodyHandler),
+            },
+        );
+        let mut service = HttpService::new(Arc::new(builder.build()));
+        let mut post = |content_length: usize| {
+            let request = hyper::Request::post("/body")
+                .header("Content-Length", content_length.to_string().as_str())
+                .body(hyper::Body::from("hello, world"))
+                .unwrap();
+            service.call(request).wait().unwrap()
+        };
+
+        let response = post(12);
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = post(5);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn rejects_chunked_requests_with_content_length() {
+        let service = || {
+            let mut builder = Router::builder();
+            builder.route(
+                Method::POST,
+                "/body".to_string(),
+                Endpoint {
+                    handler: Box::new(ReadBodyHandler),
+                },
+            );
+            HttpService::new(Arc::new(builder.build()))
+        };
+        let request = |content_length: Option<&str>| {
+            let mut request = hyper::Request::post("/body");
+            request.header(TRANSFER_ENCODING, "chunked");
+            if let Some(content_length) = content_length {
+                request.header(CONTENT_LENGTH, content_length);
+            }
+            request.body(hyper::Body::from("hello, world")).unwrap()
+        };
+
+        let response = service().call(request(None)).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = service().call(request(Some("5"))).wait().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: SerializableError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.code(), "INVALID_ARGUMENT");
+        assert_eq!(error.name(), "Default:ConflictingContentLength");
+
+        // Once allowed, the Content-Length is ignored rather than checked against the body.
+        let response = service()
+            .with_chunked_content_length_allowed(true)
+            .call(request(Some("5")))
+            .wait()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn rejects_chunked_requests_with_content_length_over_the_wire() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::POST,
+            "/body".to_string(),
+            Endpoint {
+                handler: Box::new(ReadBodyHandler),
+            },
+        );
+        let router = Arc::new(builder.build());
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service_fn(
+            move |_: &AddrStream| future::ok::<_, hyper::Error>(HttpService::new(router.clone())),
+        ));
+        let addr = server.local_addr();
+        let mut runtime = Runtime::new().unwrap();
+        runtime.spawn(server.map_err(|e| panic!("server error: {}", e)));
+
+        let post = |headers: &str| {
+            let mut socket = TcpStream::connect(addr).unwrap();
+            write!(
+                socket,
+                "POST /body HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\r\n\
+                 5\r\nhello\r\n0\r\n\r\n",
+                headers
+            ).unwrap();
+            let mut response = String::new();
+            socket.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = post("Content-Length: 5\r\nTransfer-Encoding: chunked\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 "), "{}", response);
+        assert!(
+            response.contains("Default:ConflictingContentLength"),
+            "{}",
+            response
+        );
+
+        // In this order hyper drops the Content-Length while parsing, so the body is just chunked.
+        let response = post("Transfer-Encoding: chunked\r\nContent-Length: 5\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 "), "{}", response);
+    }
+
+    #[test]
+    fn replays_responses_to_repeated_idempotency_keys() {
+        let mut builder = Router::builder();
+        builder.route(
+            Method::POST,
+            "/count".to_string(),
+            Endpoint {
+                handler: Box::new(CountingHandler(AtomicUsize::new(0))),
+            },
+        );
+        let cache = Arc::new(IdempotencyCache::new());
+        let mut service =
+            HttpService::new(Arc::new(builder.build())).with_idempotency_cache(cache.clone());
+        let mut post = |key: Option<&str>| {
+            let mut request = hyper::Request::post("/count");
+            if let Some(key) = key {
+                request.header("Idempotency-Key", key);
+            }
+            let request = request.body(hyper::Body::empty()).unwrap();
+            let response = service.call(request).wait().unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            response.into_body().concat2().wait().unwrap()
+        };
+
+        assert_eq!(&post(Some("a"))[..], b"1");
+        assert_eq!(&post(Some("a"))[..], b"1");
+        assert_eq!(cache.deduplicated(), 1);
+
+        assert_eq!(&post(Some("b"))[..], b"2");
+        assert_eq!(&post(None)[..], b"3");
+        assert_eq!(&post(None)[..], b"4");
+        assert_eq!(cache.deduplicated(), 1);
+    }
+}
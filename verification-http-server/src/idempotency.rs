@@ -0,0 +1,176 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deduplication of requests by an idempotency key, for verifying that clients retry safely.
+
+use bytes::Bytes;
+use hyper::{self, HeaderMap, Method, StatusCode};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The request header carrying the client-chosen idempotency key.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Once this many responses are cached, responses to new keys are no longer cached.
+const MAX_CACHED_RESPONSES: usize = 1024;
+
+/// Remembers the response to each request carrying an `Idempotency-Key` header, and replays it to
+/// later requests with the same method, path and key instead of handling them again.
+///
+/// Responses with a 5xx status aren't cached, so that clients can retry after server errors.
+/// Concurrent requests with the same key may both be handled.
+pub struct IdempotencyCache {
+    responses: Mutex<HashMap<CacheKey, CachedResponse>>,
+    deduplicated: AtomicUsize,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct CacheKey {
+    method: Method,
+    path: String,
+    key: String,
+}
+
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> IdempotencyCache {
+        IdempotencyCache::new()
+    }
+}
+
+impl IdempotencyCache {
+    pub fn new() -> IdempotencyCache {
+        IdempotencyCache {
+            responses: Mutex::new(HashMap::new()),
+            deduplicated: AtomicUsize::new(0),
+        }
+    }
+
+    /// The number of requests that were answered with a cached response.
+    pub fn deduplicated(&self) -> usize {
+        self.deduplicated.load(Ordering::SeqCst)
+    }
+
+    /// Returns a copy of the response cached for the given request, if any.
+    pub(crate) fn get(&self, key: &IdempotencyKey) -> Option<hyper::Response<hyper::Body>> {
+        let responses = self.responses.lock().unwrap();
+        let cached = responses.get(&key.0)?;
+        self.deduplicated.fetch_add(1, Ordering::SeqCst);
+        info!(
+            "replaying cached response. method: {}, path: {}, idempotency_key: {}",
+            key.0.method, key.0.path, key.0.key
+        );
+
+        let mut response = hyper::Response::new(hyper::Body::from(cached.body.clone()));
+        *response.status_mut() = cached.status;
+        *response.headers_mut() = cached.headers.clone();
+        Some(response)
+    }
+
+    pub(crate) fn insert(
+        &self,
+        key: IdempotencyKey,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: Bytes,
+    ) {
+        if status.is_server_error() {
+            return;
+        }
+        let mut responses = self.responses.lock().unwrap();
+        if responses.len() >= MAX_CACHED_RESPONSES {
+            return;
+        }
+        responses.insert(
+            key.0,
+            CachedResponse {
+                status,
+                headers: headers.clone(),
+                body,
+            },
+        );
+    }
+}
+
+/// Identifies a request for the purpose of deduplication.
+pub(crate) struct IdempotencyKey(CacheKey);
+
+impl IdempotencyKey {
+    /// Returns the key of the request, or `None` if it has no usable `Idempotency-Key` header.
+    pub fn of<T>(request: &hyper::Request<T>) -> Option<IdempotencyKey> {
+        let key = request.headers().get(IDEMPOTENCY_KEY)?.to_str().ok()?;
+        Some(IdempotencyKey(CacheKey {
+            method: request.method().clone(),
+            path: request.uri().path().to_string(),
+            key: key.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(method: Method, path: &str, key: &str) -> hyper::Request<()> {
+        hyper::Request::builder()
+            .method(method)
+            .uri(path)
+            .header(IDEMPOTENCY_KEY, key)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn caches_by_method_path_and_key() {
+        let cache = IdempotencyCache::new();
+        let key = || IdempotencyKey::of(&request(Method::POST, "/a", "1")).unwrap();
+        assert!(cache.get(&key()).is_none());
+
+        cache.insert(key(), StatusCode::OK, &HeaderMap::new(), Bytes::from("body"));
+        assert_eq!(cache.get(&key()).unwrap().status(), StatusCode::OK);
+        assert_eq!(cache.deduplicated(), 1);
+
+        for other in &[
+            request(Method::PUT, "/a", "1"),
+            request(Method::POST, "/b", "1"),
+            request(Method::POST, "/a", "2"),
+        ] {
+            assert!(cache.get(&IdempotencyKey::of(other).unwrap()).is_none());
+        }
+        assert_eq!(cache.deduplicated(), 1);
+
+        let no_key = hyper::Request::post("/a").body(()).unwrap();
+        assert!(IdempotencyKey::of(&no_key).is_none());
+    }
+
+    #[test]
+    fn server_errors_are_not_cached() {
+        let cache = IdempotencyCache::new();
+        let key = || IdempotencyKey::of(&request(Method::POST, "/a", "1")).unwrap();
+
+        cache.insert(
+            key(),
+            StatusCode::SERVICE_UNAVAILABLE,
+            &HeaderMap::new(),
+            Bytes::new(),
+        );
+        assert!(cache.get(&key()).is_none());
+    }
+}
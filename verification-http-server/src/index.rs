@@ -0,0 +1,107 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A root endpoint describing the running service, so that `GET /` is more useful than a 404.
+
+use bytes::Bytes;
+use conjure_verification_error::Result;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::header::HeaderValue;
+use hyper::{Method, StatusCode};
+use request::Request;
+use response::{Body, Response};
+use router::{Builder, Endpoint, Handle};
+
+/// Responds with a small JSON object naming the service, the mode it runs in and its version.
+pub struct IndexHandler {
+    body: Bytes,
+}
+
+impl IndexHandler {
+    pub fn new(name: &str, mode: &str, version: &str) -> IndexHandler {
+        let index = json!({
+            "name": name,
+            "mode": mode,
+            "version": version,
+        });
+        IndexHandler {
+            body: index.to_string().into(),
+        }
+    }
+
+    /// Serves this index at `GET /`.
+    pub fn register(self, builder: &mut Builder) {
+        builder.route(
+            Method::GET,
+            "/".to_string(),
+            Endpoint {
+                handler: Box::new(self),
+            },
+        );
+    }
+}
+
+impl Handle for IndexHandler {
+    fn handle(&self, _: &mut Request) -> Result<Response> {
+        let mut response = Response::new(StatusCode::OK);
+        response
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        response
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(self.body.len()));
+        response.body = Body::Fixed(self.body.clone());
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::{Future, Stream};
+    use handler::HttpService;
+    use hyper;
+    use hyper::service::Service;
+    use router::Router;
+    use serde_json;
+    use std::sync::Arc;
+
+    fn get(router: Router) -> hyper::Response<hyper::Body> {
+        let request = hyper::Request::get("/")
+            .body(hyper::Body::empty())
+            .unwrap();
+        HttpService::new(Arc::new(router))
+            .call(request)
+            .wait()
+            .unwrap()
+    }
+
+    #[test]
+    fn serves_index_at_root() {
+        let response = get(Router::builder().build());
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let mut builder = Router::builder();
+        IndexHandler::new("my-service", "verify-clients", "1.2.3").register(&mut builder);
+        let response = get(builder.build());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+        let body = response.into_body().concat2().wait().unwrap();
+        let index: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            index,
+            json!({"name": "my-service", "mode": "verify-clients", "version": "1.2.3"})
+        );
+    }
+}
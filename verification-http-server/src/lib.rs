@@ -27,6 +27,7 @@ extern crate hyper;
 extern crate itertools;
 extern crate mime;
 extern crate route_recognizer;
+#[macro_use]
 extern crate serde_json;
 extern crate tokio;
 extern crate typed_headers;
@@ -46,8 +47,13 @@ use response::Response;
 use router::Binder;
 use std::sync::Arc;
 
+pub mod compression;
 pub mod error_handling;
+pub mod etag;
 pub mod handler;
+pub mod idempotency;
+pub mod index;
+pub mod rate_limit;
 pub mod router;
 
 pub fn register_resource<T>(builder: &mut router::Builder, resource: &Arc<T>)
@@ -0,0 +1,132 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-client rate limiting, using a token bucket for each source IP address.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Once this many clients are tracked, buckets that have fully refilled are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+pub struct RateLimiter {
+    capacity: u32,
+    refill_period: Duration,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Allows bursts of up to `capacity` requests per client, refilling at a rate of `capacity`
+    /// requests per `refill_period`.
+    pub fn new(capacity: u32, refill_period: Duration) -> RateLimiter {
+        assert!(capacity > 0, "rate limiter capacity must be positive");
+        RateLimiter {
+            capacity,
+            refill_period,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token from the client's bucket.
+    ///
+    /// Returns how long the client should wait before retrying if the bucket is empty.
+    pub fn acquire(&self, client: IpAddr) -> Result<(), Duration> {
+        self.acquire_at(client, Instant::now())
+    }
+
+    fn acquire_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(self.capacity);
+        let rate = self.tokens_per_sec();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| bucket.tokens_at(now, rate, capacity) < capacity);
+        }
+
+        let bucket = buckets.entry(client).or_insert_with(|| Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+        bucket.tokens = bucket.tokens_at(now, rate, capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait_secs = (1.0 - bucket.tokens) / rate;
+            Err(Duration::from_millis((wait_secs * 1000.0).ceil() as u64))
+        }
+    }
+
+    fn tokens_per_sec(&self) -> f64 {
+        let period = self.refill_period.as_secs() as f64
+            + f64::from(self.refill_period.subsec_nanos()) / 1e9;
+        f64::from(self.capacity) / period
+    }
+}
+
+impl Bucket {
+    fn tokens_at(&self, now: Instant, rate: f64, capacity: f64) -> f64 {
+        let elapsed = now.duration_since(self.last_refill);
+        let elapsed_secs = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        (self.tokens + elapsed_secs * rate).min(capacity)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn limits_bursts_per_client() {
+        let limiter = RateLimiter::new(3, Duration::from_secs(3));
+        let client = "10.0.0.1".parse().unwrap();
+        let other_client = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire_at(client, start), Ok(()));
+        }
+        assert_eq!(
+            limiter.acquire_at(client, start),
+            Err(Duration::from_secs(1))
+        );
+        // Other clients have their own bucket.
+        assert_eq!(limiter.acquire_at(other_client, start), Ok(()));
+
+        // One token is regained per second.
+        let later = start + Duration::from_millis(500);
+        assert_eq!(
+            limiter.acquire_at(client, later),
+            Err(Duration::from_millis(500))
+        );
+        let later = start + Duration::from_secs(1);
+        assert_eq!(limiter.acquire_at(client, later), Ok(()));
+        assert!(limiter.acquire_at(client, later).is_err());
+
+        // After a full window, the whole burst is available again.
+        let later = start + Duration::from_secs(10);
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire_at(client, later), Ok(()));
+        }
+        assert!(limiter.acquire_at(client, later).is_err());
+    }
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use conjure_verification_error::Result;
+use hyper::HeaderMap;
 use hyper::Method;
 use request::Request;
 use resource::{NewRoute, Resource, Route};
@@ -26,6 +27,13 @@ pub trait Handle {
     fn handle(&self, request: &mut Request) -> Result<Response>;
 }
 
+/// A hook that can modify every response produced by a handler, just before it is sent.
+///
+/// Useful for cross-cutting concerns such as adding headers to all responses.
+pub trait Middleware {
+    fn on_response(&self, request_headers: &HeaderMap, response: &mut Response);
+}
+
 pub struct Endpoint {
     pub handler: Box<Handle + Sync + Send>,
 }
@@ -43,11 +51,20 @@ struct Pattern {
 
 pub struct Router {
     router: route_recognizer::Router<Pattern>,
+    middleware: Vec<Arc<Middleware + Sync + Send>>,
 }
 
 impl Router {
     pub fn builder() -> Builder {
-        Builder(HashMap::new())
+        Builder {
+            routes: HashMap::new(),
+            middleware: vec![],
+        }
+    }
+
+    /// The middleware to apply to responses, in registration order.
+    pub fn middleware(&self) -> &[Arc<Middleware + Sync + Send>] {
+        &self.middleware
     }
 
     pub fn route(&self, method: &Method, path: &str) -> RouteResult {
@@ -81,12 +98,15 @@ pub enum RouteResult {
     NotFound,
 }
 
-pub struct Builder(HashMap<Arc<str>, HashMap<Method, Endpoint>>);
+pub struct Builder {
+    routes: HashMap<Arc<str>, HashMap<Method, Endpoint>>,
+    middleware: Vec<Arc<Middleware + Sync + Send>>,
+}
 
 impl Builder {
     pub fn route(&mut self, method: Method, pattern: String, endpoint: Endpoint) -> &mut Endpoint {
         match self
-            .0
+            .routes
             .entry(pattern.into())
             .or_insert_with(HashMap::new)
             .entry(method)
@@ -100,9 +120,19 @@ impl Builder {
         }
     }
 
+    /// Registers middleware that is applied to every response, after any previously registered
+    /// middleware.
+    pub fn middleware<M>(&mut self, middleware: M) -> &mut Builder
+    where
+        M: Middleware + Sync + Send + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
     pub fn build(self) -> Router {
         let mut router = route_recognizer::Router::new();
-        for (pattern, endpoints) in self.0 {
+        for (pattern, endpoints) in self.routes {
             let value = Pattern {
                 pattern: pattern.clone(),
                 endpoints: endpoints
@@ -112,7 +142,10 @@ impl Builder {
             };
             router.add(&pattern, value);
         }
-        Router { router }
+        Router {
+            router,
+            middleware: self.middleware,
+        }
     }
 }
 
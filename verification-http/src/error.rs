@@ -39,8 +39,17 @@ pub enum ConjureVerificationError {
     MissingAuthToken,
     #[error_type(code = "InvalidArgument")]
     InvalidUrl,
+    #[error_type(code = "InvalidArgument")]
+    ConflictingContentLength,
     #[error_type(code = "CustomClient")]
     ClientIo,
     #[error_type(code = "RequestEntityTooLarge")]
     RequestEntityTooLarge,
+    #[error_type(code = "Internal")]
+    UnexpectedBodyBytesRead {
+        #[error_type(safe)]
+        expected: u64,
+        #[error_type(safe)]
+        actual: u64,
+    },
 }
@@ -21,13 +21,17 @@ use serde_json;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::error::Error as StdError;
-use std::io::Read;
+use std::io::{self, Read};
+use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use typed_headers::{Accept, Authorization, ContentType, HeaderMapExt, QualityItem};
 
 use SerializableFormat;
 
 const BODY_SIZE_LIMIT_BYTES: u64 = 1024 * 1024;
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
 
 pub struct Request<'a> {
     path_params: &'a HashMap<String, String>,
@@ -35,6 +39,9 @@ pub struct Request<'a> {
     headers: &'a HeaderMap,
     body: &'a mut Read,
     body_size_limit: u64,
+    body_bytes_read: Option<Arc<AtomicUsize>>,
+    raw_path_params: Option<&'a HashMap<String, String>>,
+    peer_addr: Option<SocketAddr>,
 }
 
 impl<'a> Request<'a> {
@@ -50,9 +57,66 @@ impl<'a> Request<'a> {
             headers,
             body,
             body_size_limit: BODY_SIZE_LIMIT_BYTES,
+            body_bytes_read: None,
+            raw_path_params: None,
+            peer_addr: None,
         }
     }
 
+    /// Shares a count of the raw body bytes read from the connection, before any decoding.
+    pub fn with_body_bytes_read(mut self, body_bytes_read: Arc<AtomicUsize>) -> Request<'a> {
+        self.body_bytes_read = Some(body_bytes_read);
+        self
+    }
+
+    /// The number of raw body bytes read so far, if the server tracks it.
+    pub fn body_bytes_read(&self) -> Option<u64> {
+        self.body_bytes_read
+            .as_ref()
+            .map(|bytes_read| bytes_read.load(AtomicOrdering::SeqCst) as u64)
+    }
+
+    /// Asserts that exactly `expected` raw body bytes have been read, e.g. to catch a handler
+    /// that stopped reading a streaming body early.
+    pub fn check_body_bytes_read(&self, expected: u64) -> Result<()> {
+        let actual = self.body_bytes_read().ok_or_else(|| {
+            Error::internal_safe("the number of body bytes read is not being tracked")
+        })?;
+        if actual != expected {
+            return Err(Error::new_safe(
+                "read an unexpected number of body bytes",
+                ConjureVerificationError::UnexpectedBodyBytesRead { expected, actual },
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sets the address of the client at the other end of the connection.
+    pub fn with_peer_addr(mut self, peer_addr: SocketAddr) -> Request<'a> {
+        self.peer_addr = Some(peer_addr);
+        self
+    }
+
+    /// The address of the client that sent this request, if the server knows it.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Shares the path params as they appeared in the request URI, before percent-decoding.
+    pub fn with_raw_path_params(
+        mut self,
+        raw_path_params: &'a HashMap<String, String>,
+    ) -> Request<'a> {
+        self.raw_path_params = Some(raw_path_params);
+        self
+    }
+
+    /// The named path param as it appeared in the request URI, if the server tracks it.
+    pub fn raw_path_param(&self, name: &str) -> Option<&str> {
+        self.raw_path_params
+            .map(|params| params.get(name).expect("invalid path param").as_str())
+    }
+
     pub fn path_param(&self, name: &str) -> &str {
         self.path_params.get(name).expect("invalid path param")
     }
@@ -129,6 +193,22 @@ impl<'a> Request<'a> {
     }
 
     pub fn body<T>(&mut self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.deserialize_body(false)
+    }
+
+    /// Like `body`, but skips a UTF-8 byte order mark at the start of a JSON body rather than
+    /// rejecting it.
+    pub fn body_skipping_bom<T>(&mut self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.deserialize_body(true)
+    }
+
+    fn deserialize_body<T>(&mut self, skip_bom: bool) -> Result<T>
     where
         T: DeserializeOwned,
     {
@@ -155,10 +235,20 @@ impl<'a> Request<'a> {
         let mut reader = self.body.take(self.body_size_limit);
 
         let (is_io, error): (bool, Box<StdError + Sync + Send>) = match format {
-            SerializableFormat::Json => match serde_json::from_reader(&mut reader) {
-                Ok(t) => return Ok(t),
-                Err(e) => (e.is_io(), Box::new(e)),
-            },
+            SerializableFormat::Json => {
+                let prefix = if skip_bom {
+                    match read_bom_prefix(&mut reader) {
+                        Ok(prefix) => prefix,
+                        Err(e) => return Err(Error::new(e, ConjureVerificationError::ClientIo)),
+                    }
+                } else {
+                    vec![]
+                };
+                match serde_json::from_reader((&prefix[..]).chain(&mut reader)) {
+                    Ok(t) => return Ok(t),
+                    Err(e) => (e.is_io(), Box::new(e)),
+                }
+            }
         };
 
         // this could be technically incorrect if the deserialization hits some other error after reading exactly 50MB,
@@ -290,6 +380,21 @@ fn quality_order(a: &QualityItem<Mime>, b: &QualityItem<Mime>) -> Ordering {
     a.item.params().count().cmp(&b.item.params().count())
 }
 
+/// Reads the start of a body, returning the bytes read unless they were a UTF-8 byte order mark.
+fn read_bom_prefix<R>(reader: &mut R) -> io::Result<Vec<u8>>
+where
+    R: Read,
+{
+    let mut prefix = Vec::with_capacity(UTF8_BOM.len());
+    reader
+        .take(UTF8_BOM.len() as u64)
+        .read_to_end(&mut prefix)?;
+    if prefix == UTF8_BOM {
+        prefix.clear();
+    }
+    Ok(prefix)
+}
+
 #[cfg(test)]
 mod test {
     use mime::APPLICATION_JSON;
@@ -334,4 +439,52 @@ mod test {
 
         assert!(request.body::<Vec<u32>>().is_err());
     }
+
+    #[test]
+    fn bom_prefixed_body() {
+        let mut headers = HeaderMap::new();
+        headers.typed_insert(&ContentType(APPLICATION_JSON));
+
+        let query_params = HashMap::new();
+        let path_params = HashMap::new();
+
+        let mut body: &[u8] = b"\xEF\xBB\xBF[1, 2]";
+        let mut request = Request::new(&path_params, &query_params, &headers, &mut body);
+        let error = request.body::<Vec<u32>>().unwrap_err();
+        assert_eq!(error.name(), "Default:InvalidRequestBody");
+
+        let mut body: &[u8] = b"\xEF\xBB\xBF[1, 2]";
+        let mut request = Request::new(&path_params, &query_params, &headers, &mut body);
+        assert_eq!(request.body_skipping_bom::<Vec<u32>>().unwrap(), vec![1, 2]);
+
+        for json in &[&b"[1, 2]"[..], b"[]", b"1", b""] {
+            let mut body = *json;
+            let mut request = Request::new(&path_params, &query_params, &headers, &mut body);
+            assert_eq!(
+                request.body_skipping_bom::<serde_json::Value>().ok(),
+                serde_json::from_slice(json).ok()
+            );
+        }
+    }
+
+    #[test]
+    fn checks_body_bytes_read() {
+        let mut body: &[u8] = b"";
+        let headers = HeaderMap::new();
+        let query_params = HashMap::new();
+        let path_params = HashMap::new();
+
+        let request = Request::new(&path_params, &query_params, &headers, &mut body);
+        assert_eq!(request.body_bytes_read(), None);
+        assert!(request.check_body_bytes_read(0).is_err());
+
+        let bytes_read = Arc::new(AtomicUsize::new(5));
+        let request = request.with_body_bytes_read(bytes_read.clone());
+        assert_eq!(request.body_bytes_read(), Some(5));
+        assert!(request.check_body_bytes_read(5).is_ok());
+
+        bytes_read.store(3, AtomicOrdering::SeqCst);
+        let error = request.check_body_bytes_read(5).unwrap_err();
+        assert_eq!(error.name(), "Default:UnexpectedBodyBytesRead");
+    }
 }
@@ -0,0 +1,246 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Startup check that the endpoints referenced by the test cases match the endpoints defined in
+//! the Conjure IR.
+
+use conjure_verification_common::type_mapping::ParamTypes;
+use conjure_verification_common::type_mapping::TestType;
+use conjure_verification_error::Error;
+use conjure_verification_error::Result;
+use errors::VerificationError;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::str::FromStr;
+use test_spec::ClientTestCases;
+use test_spec::EndpointName;
+
+/// What to do when the test cases and the IR disagree about which endpoints exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsistencyMode {
+    /// Log every inconsistency, then carry on.
+    Warn,
+    /// Fail if any test case references an endpoint that is missing from the IR.
+    Error,
+}
+
+impl Default for ConsistencyMode {
+    fn default() -> ConsistencyMode {
+        ConsistencyMode::Warn
+    }
+}
+
+impl FromStr for ConsistencyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<ConsistencyMode, String> {
+        match s {
+            "warn" => Ok(ConsistencyMode::Warn),
+            "error" => Ok(ConsistencyMode::Error),
+            other => Err(format!(
+                "Unknown consistency mode '{}', expected 'warn' or 'error'",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// A test case exists for an endpoint that the IR does not define.
+    MissingFromIr(TestType, EndpointName),
+    /// The IR defines an endpoint that has no test cases.
+    MissingFromTestCases(TestType, EndpointName),
+}
+
+/// Returns every endpoint that is present on only one of the two sides, sorted by endpoint name.
+pub fn find_inconsistencies(
+    param_types: &ParamTypes,
+    client_test_cases: &ClientTestCases,
+) -> Vec<Inconsistency> {
+    let test_case_endpoints = vec![
+        (
+            TestType::Body,
+            client_test_cases
+                .auto_deserialize
+                .keys()
+                .collect::<Vec<_>>(),
+        ),
+        (
+            TestType::SinglePathParam,
+            client_test_cases
+                .single_path_param_service
+                .keys()
+                .collect(),
+        ),
+        (
+            TestType::SingleQueryParam,
+            client_test_cases
+                .single_query_param_service
+                .keys()
+                .collect(),
+        ),
+        (
+            TestType::SingleHeaderParam,
+            client_test_cases.single_header_service.keys().collect(),
+        ),
+    ];
+
+    let empty = HashMap::new();
+    let mut inconsistencies = vec![];
+    for (test_type, endpoints) in test_case_endpoints {
+        let ir_endpoints = param_types.get(&test_type).unwrap_or(&empty);
+        inconsistencies.extend(
+            endpoints
+                .iter()
+                .filter(|endpoint| !ir_endpoints.contains_key(endpoint))
+                .sorted_by_key(|endpoint| &endpoint.0)
+                .into_iter()
+                .map(|endpoint| {
+                    Inconsistency::MissingFromIr(test_type.clone(), (*endpoint).clone())
+                }),
+        );
+        inconsistencies.extend(
+            ir_endpoints
+                .keys()
+                .filter(|endpoint| !endpoints.contains(endpoint))
+                .sorted_by_key(|endpoint| &endpoint.0)
+                .into_iter()
+                .map(|endpoint| {
+                    Inconsistency::MissingFromTestCases(test_type.clone(), endpoint.clone())
+                }),
+        );
+    }
+    inconsistencies
+}
+
+/// Logs all inconsistencies between the IR and the test cases, failing if the mode requires it.
+///
+/// Endpoints that are defined in the IR but have no test cases are only ever logged, because
+/// they don't prevent the remaining test cases from being resolved.
+pub fn check_consistency(
+    mode: ConsistencyMode,
+    param_types: &ParamTypes,
+    client_test_cases: &ClientTestCases,
+) -> Result<()> {
+    let inconsistencies = find_inconsistencies(param_types, client_test_cases);
+    for inconsistency in &inconsistencies {
+        match inconsistency {
+            Inconsistency::MissingFromIr(test_type, endpoint) => warn!(
+                "{:?} test cases reference endpoint '{}' which is not in the IR",
+                test_type, endpoint
+            ),
+            Inconsistency::MissingFromTestCases(test_type, endpoint) => warn!(
+                "IR defines {:?} endpoint '{}' which has no test cases",
+                test_type, endpoint
+            ),
+        }
+    }
+
+    let missing_from_ir = inconsistencies
+        .iter()
+        .filter_map(|inconsistency| match inconsistency {
+            Inconsistency::MissingFromIr(_, endpoint) => Some(endpoint.0.as_str()),
+            Inconsistency::MissingFromTestCases(..) => None,
+        }).join(", ");
+    if mode == ConsistencyMode::Error && !missing_from_ir.is_empty() {
+        return Err(Error::new_safe(
+            "Test cases reference endpoints that are not in the IR",
+            VerificationError::InconsistentTestCases {
+                endpoints: missing_from_ir,
+            },
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use conjure::ir::PrimitiveType;
+    use conjure::resolved_type::ResolvedType;
+    use conjure_verification_common::type_mapping::builder::ParamTypesBuilder;
+    use resolved_test_cases::resolve_test_cases;
+
+    fn setup() -> (ParamTypes, ClientTestCases) {
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::SingleHeaderParam,
+            EndpointName::new("headerString"),
+            ResolvedType::Primitive(PrimitiveType::String),
+        );
+        param_types.add(
+            TestType::SingleHeaderParam,
+            EndpointName::new("headerInteger"),
+            ResolvedType::Primitive(PrimitiveType::Integer),
+        );
+        let mut test_cases = ClientTestCases::default();
+        test_cases.single_header_service = hashmap!(
+            EndpointName::new("headerString") => vec!["\"yo\"".into()],
+            EndpointName::new("headerTypo") => vec!["\"yo\"".into()]
+        );
+        (param_types.build(), test_cases)
+    }
+
+    #[test]
+    fn finds_mismatched_endpoints() {
+        let (param_types, test_cases) = setup();
+        assert_eq!(
+            find_inconsistencies(&param_types, &test_cases),
+            vec![
+                Inconsistency::MissingFromIr(
+                    TestType::SingleHeaderParam,
+                    EndpointName::new("headerTypo")
+                ),
+                Inconsistency::MissingFromTestCases(
+                    TestType::SingleHeaderParam,
+                    EndpointName::new("headerInteger")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn mode_determines_failure() {
+        let (param_types, test_cases) = setup();
+        assert!(check_consistency(ConsistencyMode::Warn, &param_types, &test_cases).is_ok());
+
+        let error =
+            check_consistency(ConsistencyMode::Error, &param_types, &test_cases).unwrap_err();
+        assert_eq!(error.name(), "ConjureVerification:InconsistentTestCases");
+    }
+
+    #[test]
+    fn warn_mode_skips_endpoints_missing_from_ir() {
+        let (param_types, test_cases) = setup();
+        check_consistency(ConsistencyMode::Warn, &param_types, &test_cases).unwrap();
+
+        let resolved = resolve_test_cases(&param_types, &test_cases).unwrap();
+        assert_eq!(
+            resolved.single_header_service.keys().collect::<Vec<_>>(),
+            vec![&EndpointName::new("headerString")]
+        );
+    }
+
+    #[test]
+    fn consistent_endpoints_pass() {
+        let (param_types, mut test_cases) = setup();
+        test_cases.single_header_service = hashmap!(
+            EndpointName::new("headerString") => vec!["\"yo\"".into()],
+            EndpointName::new("headerInteger") => vec!["1".into()]
+        );
+        assert_eq!(find_inconsistencies(&param_types, &test_cases), vec![]);
+        assert!(check_consistency(ConsistencyMode::Error, &param_types, &test_cases).is_ok());
+    }
+}
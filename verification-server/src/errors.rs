@@ -12,11 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use conjure::value::ser;
 use conjure::value::ConjureValue;
 use serde_json;
 use std::fmt::Display;
 use test_spec::EndpointName;
 
+const MAX_DISPLAYED_VALUE_LENGTH: usize = 4096;
+
 #[derive(ErrorType)]
 #[error_type(namespace = "ConjureVerification")]
 pub enum VerificationError {
@@ -65,8 +68,20 @@ pub enum VerificationError {
         #[error_type(safe)]
         max_index: usize,
     },
+    #[error_type(code = "InvalidArgument")]
+    NegativeTestCaseSkipped {
+        #[error_type(safe)]
+        index: usize,
+        #[error_type(safe)]
+        max_negatives: usize,
+    },
     #[error_type(code = "CustomClient")]
     ClientIo,
+    #[error_type(code = "FailedPrecondition")]
+    InconsistentTestCases {
+        #[error_type(safe)]
+        endpoints: String,
+    },
     #[error_type(code = "CustomServer")]
     BadTestCase {
         #[error_type(safe)]
@@ -76,6 +91,18 @@ pub enum VerificationError {
         #[error_type(safe)]
         value: String,
     },
+    #[error_type(code = "InvalidArgument")]
+    UnencodedPathParam {
+        #[error_type(safe)]
+        raw_param: String,
+    },
+    #[error_type(code = "InvalidArgument")]
+    MissingUserAgent,
+    #[error_type(code = "InvalidArgument")]
+    InvalidUserAgent {
+        #[error_type(safe)]
+        user_agent: String,
+    },
 }
 
 impl VerificationError {
@@ -124,6 +151,6 @@ impl VerificationError {
     }
 
     fn display_conjure_value(value: &ConjureValue) -> String {
-        format!("{:?}", value)
+        ser::to_display_string(value, MAX_DISPLAYED_VALUE_LENGTH)
     }
 }
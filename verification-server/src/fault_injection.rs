@@ -0,0 +1,112 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Artificial failures and latency, used to check that clients under test retry correctly.
+//!
+//! Faults are requested per call using query parameters:
+//! * `failFirst=N` - the first `N` requests for a given endpoint and index fail.
+//! * `failWith=503|429` - the status code of injected failures, defaulting to 503.
+//! * `delayMillis=N` - every request is delayed by `N` milliseconds before being handled.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use conjure_verification_error::{Code, Error, Result};
+use conjure_verification_http::request::Request;
+
+pub const FAIL_FIRST_PARAM: &str = "failFirst";
+pub const FAIL_WITH_PARAM: &str = "failWith";
+pub const DELAY_MILLIS_PARAM: &str = "delayMillis";
+
+/// The faults requested by a single request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultSpec {
+    pub fail_first: usize,
+    pub fail_with: Code,
+    pub delay: Option<Duration>,
+}
+
+impl Default for FaultSpec {
+    fn default() -> FaultSpec {
+        FaultSpec {
+            fail_first: 0,
+            fail_with: Code::ServiceUnavailable,
+            delay: None,
+        }
+    }
+}
+
+impl FaultSpec {
+    pub fn from_request(request: &Request) -> Result<FaultSpec> {
+        let fail_first = request
+            .opt_query_param::<usize>(FAIL_FIRST_PARAM)?
+            .unwrap_or(0);
+        let fail_with = match request.opt_query_param::<u16>(FAIL_WITH_PARAM)? {
+            None | Some(503) => Code::ServiceUnavailable,
+            Some(429) => Code::TooManyRequests,
+            Some(status) => {
+                return Err(Error::new_safe(
+                    "Unsupported failure status, expected 503 or 429",
+                    Code::InvalidArgument,
+                ).with_safe_param("status", status))
+            }
+        };
+        let delay = request
+            .opt_query_param::<u64>(DELAY_MILLIS_PARAM)?
+            .map(Duration::from_millis);
+        Ok(FaultSpec {
+            fail_first,
+            fail_with,
+            delay,
+        })
+    }
+}
+
+/// Keeps track of how many times each test has been attempted, so that only the first attempts
+/// fail.
+#[derive(Default)]
+pub struct FaultInjector {
+    attempts: Mutex<HashMap<String, usize>>,
+}
+
+impl FaultInjector {
+    /// Applies the faults requested by `request` to the test identified by `key`.
+    ///
+    /// Returns an error with the requested code if this attempt should fail.
+    pub fn inject(&self, key: &str, request: &Request) -> Result<()> {
+        let spec = FaultSpec::from_request(request)?;
+        if let Some(delay) = spec.delay {
+            thread::sleep(delay);
+        }
+        if spec.fail_first == 0 {
+            return Ok(());
+        }
+
+        let attempt = {
+            let mut attempts = self.attempts.lock().unwrap();
+            let attempt = attempts.entry(key.to_string()).or_insert(0);
+            *attempt += 1;
+            *attempt
+        };
+        if attempt <= spec.fail_first {
+            return Err(Error::new_safe("Injected failure", spec.fail_with)
+                .with_safe_param("key", key)
+                .with_safe_param("attempt", attempt)
+                .with_safe_param("failFirst", spec.fail_first));
+        }
+        Ok(())
+    }
+}
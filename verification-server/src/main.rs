@@ -30,6 +30,7 @@ extern crate hyper;
 extern crate mime;
 extern crate pretty_env_logger;
 extern crate serde_conjure;
+#[cfg_attr(test, macro_use)]
 extern crate serde_json;
 extern crate serde_plain;
 extern crate serde_yaml;
@@ -37,19 +38,33 @@ extern crate typed_headers;
 #[macro_use]
 extern crate serde_conjure_derive;
 extern crate itertools;
+#[macro_use]
+extern crate log;
 
 use conjure::ir::Conjure;
+use conjure::value::canonical_uuid::UuidPolicy;
+use conjure::value::double::IntegerDoublePolicy;
+use conjure::value::profile::StrictnessProfile;
+use consistency::ConsistencyMode;
 use conjure_verification_common::conjure;
 use conjure_verification_common::type_mapping;
 use conjure_verification_common::type_mapping::return_type;
 use conjure_verification_common::type_mapping::type_of_non_index_arg;
+use conjure_verification_common::type_mapping::ParamTypes;
 use conjure_verification_common::type_mapping::ServiceTypeMapping;
 use conjure_verification_common::type_mapping::TestType;
 use conjure_verification_error::Result;
 pub use conjure_verification_http_server::*;
+use compression::GzipMiddleware;
+use etag::ETagMiddleware;
 use futures::{future, Future};
 use handler::HttpService;
+use hyper::server::conn::AddrStream;
+use hyper::service::make_service_fn;
 use hyper::Server;
+use idempotency::IdempotencyCache;
+use index::IndexHandler;
+use rate_limit::RateLimiter;
 use resolved_test_cases::ResolvedClientTestCases;
 use resource::SpecTestResource;
 use router::Router;
@@ -60,23 +75,29 @@ use std::net::SocketAddr;
 use std::path::Path;
 use std::process;
 use std::sync::Arc;
+use std::time::Duration;
 use test_spec::ClientTestCases;
 use test_spec::TestCases;
+use user_agent::UserAgentRequirement;
 
+pub mod consistency;
 pub mod errors;
+pub mod fault_injection;
 pub mod fixed_streaming;
+pub mod ndjson_response;
 pub mod raw_json;
 pub mod resolved_test_cases;
 pub mod resource;
 pub mod test_spec;
+pub mod user_agent;
 
 #[cfg(test)]
 pub mod test;
 
 fn main() {
     pretty_env_logger::init();
-    let args = &env::args().collect::<Vec<_>>()[..];
-    if args.iter().any(|x| x == "--help") {
+    let (flags, args): (Vec<_>, Vec<_>) = env::args().partition(|arg| arg.starts_with("--"));
+    if flags.iter().any(|x| x == "--help") {
         print_usage(&args[0]);
         process::exit(0);
     }
@@ -86,6 +107,117 @@ fn main() {
         process::exit(1);
     }
 
+    let mut consistency_mode = ConsistencyMode::default();
+    // We are verifying clients, so read their requests the way a Conjure server would.
+    let mut profile = StrictnessProfile::Server;
+    let mut user_agent = UserAgentRequirement::default();
+    let mut rate_limit = None;
+    let mut etag = None;
+    let mut gzip = false;
+    let mut exact_body_reads = false;
+    let mut forbid_null_optionals = false;
+    let mut integer_doubles = IntegerDoublePolicy::default();
+    let mut uuids = UuidPolicy::default();
+    let mut max_negatives = None;
+    let mut strict_accept = false;
+    let mut strict_path_encoding = false;
+    let mut ndjson = false;
+    let mut index = false;
+    let mut dump_types = false;
+    let mut handler_timeout = None;
+    let mut idempotency_keys = false;
+    let mut allow_chunked_content_length = false;
+    for flag in &flags {
+        if flag.starts_with(CONSISTENCY_FLAG) {
+            match flag[CONSISTENCY_FLAG.len()..].parse() {
+                Ok(mode) => consistency_mode = mode,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag.starts_with(PROFILE_FLAG) {
+            match flag[PROFILE_FLAG.len()..].parse() {
+                Ok(p) => profile = p,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag.starts_with(USER_AGENT_FLAG) {
+            match flag[USER_AGENT_FLAG.len()..].parse() {
+                Ok(requirement) => user_agent = requirement,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag.starts_with(RATE_LIMIT_FLAG) {
+            match flag[RATE_LIMIT_FLAG.len()..].parse::<u32>() {
+                Ok(limit) if limit > 0 => rate_limit = Some(limit),
+                _ => invalid_flag(&args[0], flag),
+            }
+        } else if flag.starts_with(ETAG_FLAG) {
+            match ETagMiddleware::new(&flag[ETAG_FLAG.len()..]) {
+                Ok(middleware) => etag = Some(middleware),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag == GZIP_FLAG {
+            gzip = true;
+        } else if flag == EXACT_BODY_READS_FLAG {
+            exact_body_reads = true;
+        } else if flag == FORBID_NULL_OPTIONALS_FLAG {
+            forbid_null_optionals = true;
+        } else if flag.starts_with(INTEGER_DOUBLES_FLAG) {
+            match flag[INTEGER_DOUBLES_FLAG.len()..].parse() {
+                Ok(policy) => integer_doubles = policy,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag.starts_with(UUIDS_FLAG) {
+            match flag[UUIDS_FLAG.len()..].parse() {
+                Ok(policy) => uuids = policy,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    invalid_flag(&args[0], flag)
+                }
+            }
+        } else if flag.starts_with(MAX_NEGATIVES_FLAG) {
+            match flag[MAX_NEGATIVES_FLAG.len()..].parse() {
+                Ok(n) => max_negatives = Some(n),
+                Err(_) => invalid_flag(&args[0], flag),
+            }
+        } else if flag == STRICT_ACCEPT_FLAG {
+            strict_accept = true;
+        } else if flag == STRICT_PATH_ENCODING_FLAG {
+            strict_path_encoding = true;
+        } else if flag == NDJSON_FLAG {
+            ndjson = true;
+        } else if flag == INDEX_FLAG {
+            index = true;
+        } else if flag == DUMP_TYPES_FLAG {
+            dump_types = true;
+        } else if flag.starts_with(HANDLER_TIMEOUT_FLAG) {
+            match flag[HANDLER_TIMEOUT_FLAG.len()..].parse() {
+                Ok(secs) => handler_timeout = Some(Duration::from_secs(secs)),
+                Err(_) => invalid_flag(&args[0], flag),
+            }
+        } else if flag == IDEMPOTENCY_KEYS_FLAG {
+            idempotency_keys = true;
+        } else if flag == ALLOW_CHUNKED_CONTENT_LENGTH_FLAG {
+            allow_chunked_content_length = true;
+        } else {
+            eprintln!("Unknown flag: {}", flag);
+            print_usage(&args[0]);
+            process::exit(1);
+        }
+    }
+
     let port = match env::var("PORT") {
         Ok(port) => port.parse().unwrap(),
         Err(VarError::NotPresent) => 8000,
@@ -102,21 +234,97 @@ fn main() {
     let ir = File::open(Path::new(ir_path)).unwrap();
     let ir: Box<Conjure> = Box::new(serde_json::from_reader(ir).unwrap());
 
+    if dump_types {
+        let param_types = resolve_param_types(&ir);
+        println!("{}", serde_json::to_string_pretty(&param_types).unwrap());
+        return;
+    }
+
+    let resource = SpecTestResource::new(
+        resolve_test_cases(&ir, &test_cases.client, consistency_mode)
+            .unwrap()
+            .into(),
+        profile,
+    ).with_user_agent_requirement(user_agent)
+    .with_exact_body_reads(exact_body_reads)
+    .with_forbid_null_optionals(forbid_null_optionals)
+    .with_integer_doubles(integer_doubles)
+    .with_uuids(uuids)
+    .with_max_negatives(max_negatives)
+    .with_strict_accept(strict_accept)
+    .with_strict_path_encoding(strict_path_encoding)
+    .with_ndjson(ndjson);
     let mut builder = router::Router::builder();
-    register_resource(
-        &mut builder,
-        &Arc::new(SpecTestResource::new(
-            resolve_test_cases(&ir, &test_cases.client).unwrap().into(),
-        )),
-    );
+    register_resource(&mut builder, &Arc::new(resource));
+    if let Some(etag) = etag {
+        builder.middleware(etag);
+    }
+    if gzip {
+        builder.middleware(GzipMiddleware);
+    }
+    if index {
+        IndexHandler::new(
+            env!("CARGO_PKG_NAME"),
+            "verify-clients",
+            env!("CARGO_PKG_VERSION"),
+        ).register(&mut builder);
+    }
     let router = builder.build();
 
-    start_server(router, port);
+    let rate_limiter =
+        rate_limit.map(|limit| Arc::new(RateLimiter::new(limit, Duration::from_secs(1))));
+    let idempotency_cache = if idempotency_keys {
+        Some(Arc::new(IdempotencyCache::new()))
+    } else {
+        None
+    };
+    start_server(
+        router,
+        port,
+        rate_limiter,
+        handler_timeout,
+        idempotency_cache,
+        allow_chunked_content_length,
+    );
+}
+
+const CONSISTENCY_FLAG: &str = "--consistency=";
+const PROFILE_FLAG: &str = "--profile=";
+const USER_AGENT_FLAG: &str = "--user-agent=";
+const RATE_LIMIT_FLAG: &str = "--rate-limit=";
+const ETAG_FLAG: &str = "--etag=";
+const GZIP_FLAG: &str = "--gzip";
+const EXACT_BODY_READS_FLAG: &str = "--exact-body-reads";
+const FORBID_NULL_OPTIONALS_FLAG: &str = "--forbid-null-optionals";
+const INTEGER_DOUBLES_FLAG: &str = "--integer-doubles=";
+const UUIDS_FLAG: &str = "--uuids=";
+const MAX_NEGATIVES_FLAG: &str = "--max-negatives=";
+const STRICT_ACCEPT_FLAG: &str = "--strict-accept";
+const DUMP_TYPES_FLAG: &str = "--dump-types";
+const HANDLER_TIMEOUT_FLAG: &str = "--handler-timeout=";
+const STRICT_PATH_ENCODING_FLAG: &str = "--strict-path-encoding";
+const NDJSON_FLAG: &str = "--ndjson";
+const INDEX_FLAG: &str = "--index";
+const IDEMPOTENCY_KEYS_FLAG: &str = "--idempotency-keys";
+const ALLOW_CHUNKED_CONTENT_LENGTH_FLAG: &str = "--allow-chunked-content-length";
+
+/// Exits after printing the usage, for flags whose value can't be used.
+fn invalid_flag(arg0: &str, flag: &str) -> ! {
+    eprintln!("Invalid flag: {}", flag);
+    print_usage(arg0);
+    process::exit(1);
 }
 
 fn print_usage(arg0: &str) {
     eprintln!(
-        "Usage: {} <server-test-cases.json> <verification-api.conjure.json>",
+        "Usage: {} [--consistency=warn|error] [--profile=client|server] \
+         [--user-agent=none|present|conjure] [--rate-limit=<requests-per-second>] \
+         [--etag=<tag>] [--gzip] [--exact-body-reads] [--forbid-null-optionals] \
+         [--integer-doubles=lenient|strict] [--uuids=lenient|canonical] [--max-negatives=<n>] \
+         [--strict-accept] [--dump-types] \
+         [--handler-timeout=<seconds>] [--strict-path-encoding] [--ndjson] [--index] \
+         [--idempotency-keys] [--allow-chunked-content-length] \
+         <server-test-cases.json> <verification-api.conjure.json>",
         arg0
     );
 }
@@ -124,7 +332,16 @@ fn print_usage(arg0: &str) {
 pub fn resolve_test_cases(
     ir: &Conjure,
     client_test_cases: &ClientTestCases,
+    consistency_mode: ConsistencyMode,
 ) -> Result<ResolvedClientTestCases> {
+    let type_mapping = resolve_param_types(ir);
+    consistency::check_consistency(consistency_mode, &type_mapping, client_test_cases)?;
+
+    resolved_test_cases::resolve_test_cases(&type_mapping, client_test_cases)
+}
+
+/// Resolves the type that each endpoint of the test services is tested with.
+pub fn resolve_param_types(ir: &Conjure) -> ParamTypes {
     let services_mapping = vec![
         ServiceTypeMapping::new("AutoDeserializeService", TestType::Body, return_type),
         ServiceTypeMapping::new(
@@ -144,19 +361,38 @@ pub fn resolve_test_cases(
         ),
     ];
 
-    let type_mapping = type_mapping::resolve_types(ir, &services_mapping);
-
-    resolved_test_cases::resolve_test_cases(&type_mapping, client_test_cases)
+    type_mapping::resolve_types(ir, &services_mapping)
 }
 
-fn start_server(router: Router, port: u16) {
+fn start_server(
+    router: Router,
+    port: u16,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    handler_timeout: Option<Duration>,
+    idempotency_cache: Option<Arc<IdempotencyCache>>,
+    allow_chunked_content_length: bool,
+) {
     // bind to 0.0.0.0 instead of loopback so that requests can be served from docker
     let addr = SocketAddr::new("0.0.0.0".parse().unwrap(), port);
 
     let router = Arc::new(router);
 
     hyper::rt::run(future::lazy(move || {
-        let new_service = move || future::ok::<_, hyper::Error>(HttpService::new(router.clone()));
+        let new_service = make_service_fn(move |socket: &AddrStream| {
+            let mut service = HttpService::new(router.clone())
+                .with_peer_addr(socket.remote_addr())
+                .with_chunked_content_length_allowed(allow_chunked_content_length);
+            if let Some(rate_limiter) = &rate_limiter {
+                service = service.with_rate_limiter(rate_limiter.clone());
+            }
+            if let Some(handler_timeout) = handler_timeout {
+                service = service.with_handler_timeout(handler_timeout);
+            }
+            if let Some(idempotency_cache) = &idempotency_cache {
+                service = service.with_idempotency_cache(idempotency_cache.clone());
+            }
+            future::ok::<_, hyper::Error>(service)
+        });
 
         let server = Server::bind(&addr)
             .serve(new_service)
@@ -0,0 +1,72 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use conjure::value::ConjureValue;
+use conjure_verification_common::ndjson::{self, APPLICATION_NDJSON};
+use conjure_verification_error::{Error, Result};
+use conjure_verification_http::request::Request;
+use conjure_verification_http::response::IntoResponse;
+use conjure_verification_http::response::WriteBody;
+use conjure_verification_http::response::{Body, Response};
+use http::header::{HeaderValue, CONTENT_TYPE};
+use http::StatusCode;
+use serde_json;
+use std::io::Write;
+
+/// Writes values as newline-delimited JSON. The lines are serialized up front, but flushed one at
+/// a time so that clients see each value before the rest of the body arrives.
+pub struct NdjsonResponse {
+    lines: Vec<Vec<u8>>,
+}
+
+impl NdjsonResponse {
+    pub fn new<'a, I>(values: I) -> Result<NdjsonResponse>
+    where
+        I: IntoIterator<Item = &'a ConjureValue>,
+    {
+        let lines = values
+            .into_iter()
+            .map(|value| {
+                let mut line = vec![];
+                ndjson::to_writer(&mut line, Some(value)).map(|()| line)
+            }).collect::<serde_json::Result<_>>()
+            .map_err(Error::internal)?;
+        Ok(NdjsonResponse { lines })
+    }
+}
+
+impl WriteBody for NdjsonResponse {
+    fn write_body(&mut self, w: &mut Write) -> Result<()> {
+        for line in &self.lines {
+            w.write_all(line)
+                .and_then(|()| w.flush())
+                .map_err(Error::internal)?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoResponse for NdjsonResponse {
+    fn into_response(self, _request: &Request) -> Result<Response> {
+        let mut response = Response::new(StatusCode::OK);
+        response
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static(APPLICATION_NDJSON));
+        response
+            .headers
+            .append("Access-Control-Allow-Origin", HeaderValue::from_static("*"));
+        response.body = Body::Streaming(Box::new(self));
+        Ok(response)
+    }
+}
@@ -29,12 +29,12 @@
 use conjure::resolved_type::ResolvedType;
 use conjure::value::ConjureValue;
 use conjure_verification_common::more_serde_json;
+use conjure_verification_common::type_mapping::ParamTypes;
 use conjure_verification_common::type_mapping::TestType;
 use conjure_verification_error::Error;
 use conjure_verification_error::Result;
 use errors::VerificationError;
 use std::collections::HashMap;
-use std::ops::Index;
 use test_spec::ClientTestCases;
 use test_spec::EndpointName;
 use test_spec::PositiveAndNegativeTestCases;
@@ -62,24 +62,34 @@ pub struct ResolvedTestCase {
     pub text: String,
 }
 
-pub fn resolve_test_cases<'a, I, I2>(
-    type_mapping: &'a I,
-    client_test_cases: &'a ClientTestCases,
-) -> Result<ResolvedClientTestCases>
-where
-    I: Index<&'a TestType, Output = I2>,
-    I2: Index<&'a EndpointName, Output = ResolvedType>,
-{
+/// Resolves the test cases of every endpoint that the IR defines.
+///
+/// Test cases for endpoints missing from the IR are logged and skipped, so they can be tolerated
+/// in [ConsistencyMode::Warn].
+///
+/// [ConsistencyMode::Warn]: ../consistency/enum.ConsistencyMode.html
+pub fn resolve_test_cases(
+    type_mapping: &ParamTypes,
+    client_test_cases: &ClientTestCases,
+) -> Result<ResolvedClientTestCases> {
+    let resolve_params = |conjure_type: &ResolvedType,
+                          endpoint: &EndpointName,
+                          cases: &Vec<String>|
+     -> Result<ResolvedTestCases> {
+        Ok(ResolvedTestCases {
+            test_cases: resolve_cases(cases, conjure_type, endpoint).collect::<Result<Vec<_>>>()?,
+            conjure_type: conjure_type.clone(),
+        })
+    };
     Ok(ResolvedClientTestCases {
-        auto_deserialize: client_test_cases
-            .auto_deserialize
-            .iter()
-            .map(|(endpoint, cases)| {
-                // Get the conjure type
-                let conjure_type = &type_mapping[&TestType::Body][endpoint];
+        auto_deserialize: resolve_endpoints(
+            type_mapping,
+            TestType::Body,
+            &client_test_cases.auto_deserialize,
+            |conjure_type, endpoint, cases: &PositiveAndNegativeTestCases| {
                 // Parse the positive test cases
                 let positive = ResolvedTestCases {
-                    test_cases: resolve_cases(&cases.positive, &conjure_type, endpoint)
+                    test_cases: resolve_cases(&cases.positive, conjure_type, endpoint)
                         .collect::<Result<Vec<_>>>()?,
                     conjure_type: conjure_type.clone(),
                 };
@@ -88,57 +98,64 @@ where
                 // TODO(dsanduleac): enable when we have stricter parsing for datetime, uuid etc
                 // ensure_negative_cases_do_not_parse(&conjure_type, endpoint, cases)?;
 
-                let new_v = ResolvedPositiveAndNegativeTestCases {
+                Ok(ResolvedPositiveAndNegativeTestCases {
                     positive,
                     negative: cases.negative.clone(),
-                };
-                Ok((endpoint.clone(), new_v))
-            }).collect::<Result<HashMap<_, _>>>()?,
-        single_path_param_service: client_test_cases
-            .single_path_param_service
-            .iter()
-            .map(|(endpoint, cases)| {
-                let conjure_type = &type_mapping[&TestType::SinglePathParam][endpoint];
-                Ok((
-                    endpoint.clone(),
-                    ResolvedTestCases {
-                        test_cases: resolve_cases(cases, conjure_type, endpoint)
-                            .collect::<Result<Vec<_>>>()?,
-                        conjure_type: conjure_type.clone(),
-                    },
-                ))
-            }).collect::<Result<HashMap<_, _>>>()?,
-        single_query_param_service: client_test_cases
-            .single_query_param_service
-            .iter()
-            .map(|(endpoint, cases)| {
-                let conjure_type = &type_mapping[&TestType::SingleQueryParam][endpoint];
-                Ok((
-                    endpoint.clone(),
-                    ResolvedTestCases {
-                        test_cases: resolve_cases(cases, conjure_type, endpoint)
-                            .collect::<Result<Vec<_>>>()?,
-                        conjure_type: conjure_type.clone(),
-                    },
-                ))
-            }).collect::<Result<HashMap<_, _>>>()?,
-        single_header_service: client_test_cases
-            .single_header_service
-            .iter()
-            .map(|(endpoint, cases)| {
-                let conjure_type = &type_mapping[&TestType::SingleHeaderParam][endpoint];
-                Ok((
-                    endpoint.clone(),
-                    ResolvedTestCases {
-                        test_cases: resolve_cases(cases, conjure_type, endpoint)
-                            .collect::<Result<Vec<_>>>()?,
-                        conjure_type: conjure_type.clone(),
-                    },
-                ))
-            }).collect::<Result<HashMap<_, _>>>()?,
+                })
+            },
+        )?,
+        single_path_param_service: resolve_endpoints(
+            type_mapping,
+            TestType::SinglePathParam,
+            &client_test_cases.single_path_param_service,
+            resolve_params,
+        )?,
+        single_query_param_service: resolve_endpoints(
+            type_mapping,
+            TestType::SingleQueryParam,
+            &client_test_cases.single_query_param_service,
+            resolve_params,
+        )?,
+        single_header_service: resolve_endpoints(
+            type_mapping,
+            TestType::SingleHeaderParam,
+            &client_test_cases.single_header_service,
+            resolve_params,
+        )?,
     })
 }
 
+/// Resolves the test cases of each endpoint with its type from the IR, skipping endpoints that
+/// have none.
+fn resolve_endpoints<C, R, F>(
+    type_mapping: &ParamTypes,
+    test_type: TestType,
+    cases: &HashMap<EndpointName, C>,
+    resolve: F,
+) -> Result<HashMap<EndpointName, R>>
+where
+    F: Fn(&ResolvedType, &EndpointName, &C) -> Result<R>,
+{
+    let endpoint_types = type_mapping.get(&test_type);
+    cases
+        .iter()
+        .filter_map(|(endpoint, cases)| {
+            match endpoint_types.and_then(|endpoint_types| endpoint_types.get(endpoint)) {
+                Some(conjure_type) => Some(
+                    resolve(conjure_type, endpoint, cases)
+                        .map(|resolved| (endpoint.clone(), resolved)),
+                ),
+                None => {
+                    warn!(
+                        "Skipping {:?} test cases for endpoint '{}' which is not in the IR",
+                        test_type, endpoint
+                    );
+                    None
+                }
+            }
+        }).collect()
+}
+
 #[allow(dead_code)]
 fn ensure_negative_cases_do_not_parse(
     conjure_type: &ResolvedType,
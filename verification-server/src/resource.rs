@@ -21,10 +21,15 @@ use std::string::ToString;
 use bytes::Bytes;
 use either::{Either, Left, Right};
 use http::Method;
+use mime::{Mime, APPLICATION_JSON, APPLICATION_OCTET_STREAM};
 use serde_json;
 
 use conjure::value::*;
+use conjure_verification_common::conjure::value::canonical_uuid::UuidPolicy;
+use conjure_verification_common::conjure::value::double::IntegerDoublePolicy;
 use conjure_verification_common::conjure::value::de_plain::deserialize_plain;
+use conjure_verification_common::conjure::value::profile::StrictnessProfile;
+use conjure_verification_common::ndjson::APPLICATION_NDJSON;
 use conjure_verification_error::Result;
 use conjure_verification_error::{Code, Error};
 use conjure_verification_http::error::ConjureVerificationError;
@@ -38,7 +43,9 @@ use conjure_verification_http::response::Response;
 use conjure_verification_http::SerializableFormat;
 use conjure_verification_http_server::RouteWithOptions;
 use errors::*;
+use fault_injection::FaultInjector;
 use fixed_streaming::StreamingResponse;
+use ndjson_response::NdjsonResponse;
 use raw_json::RawJson;
 use resolved_test_cases::ResolvedClientTestCases;
 use resolved_test_cases::ResolvedPositiveAndNegativeTestCases;
@@ -46,15 +53,115 @@ use resolved_test_cases::ResolvedTestCase;
 use resolved_test_cases::ResolvedTestCases;
 use test_spec::EndpointName;
 use typed_headers::{ContentLength, ContentType, HeaderMapExt};
+use user_agent::UserAgentRequirement;
 use DynamicResource;
 
+/// The header used by `SingleHeaderService`.
+///
+/// Header names are case-insensitive: `HeaderMap` normalizes names on both insertion and lookup,
+/// so clients may send this in any case.
+const SINGLE_HEADER_NAME: &str = "Some-Header";
+
 pub struct SpecTestResource {
     test_cases: Box<ResolvedClientTestCases>,
+    faults: FaultInjector,
+    profile: StrictnessProfile,
+    user_agent: UserAgentRequirement,
+    exact_body_reads: bool,
+    forbid_null_optionals: bool,
+    integer_doubles: IntegerDoublePolicy,
+    uuids: UuidPolicy,
+    max_negatives: Option<usize>,
+    strict_accept: bool,
+    strict_path_encoding: bool,
+    ndjson: bool,
 }
 
 impl SpecTestResource {
-    pub fn new(test_cases: Box<ResolvedClientTestCases>) -> SpecTestResource {
-        SpecTestResource { test_cases }
+    pub fn new(
+        test_cases: Box<ResolvedClientTestCases>,
+        profile: StrictnessProfile,
+    ) -> SpecTestResource {
+        SpecTestResource {
+            test_cases,
+            faults: FaultInjector::default(),
+            profile,
+            user_agent: UserAgentRequirement::default(),
+            exact_body_reads: false,
+            forbid_null_optionals: false,
+            integer_doubles: IntegerDoublePolicy::default(),
+            uuids: UuidPolicy::default(),
+            max_negatives: None,
+            strict_accept: false,
+            strict_path_encoding: false,
+            ndjson: false,
+        }
+    }
+
+    /// Requires every request to carry a `User-Agent` that meets the given requirement.
+    pub fn with_user_agent_requirement(
+        mut self,
+        user_agent: UserAgentRequirement,
+    ) -> SpecTestResource {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Fails confirmations unless the server read exactly as many body bytes as the request's
+    /// `Content-Length` declared.
+    pub fn with_exact_body_reads(mut self, exact_body_reads: bool) -> SpecTestResource {
+        self.exact_body_reads = exact_body_reads;
+        self
+    }
+
+    /// Fails confirmations whose body sets an optional field to null instead of omitting it.
+    pub fn with_forbid_null_optionals(mut self, forbid_null_optionals: bool) -> SpecTestResource {
+        self.forbid_null_optionals = forbid_null_optionals;
+        self
+    }
+
+    /// Sets what happens to integers in request bodies that can't be represented exactly as the
+    /// doubles they're deserialized into.
+    pub fn with_integer_doubles(
+        mut self,
+        integer_doubles: IntegerDoublePolicy,
+    ) -> SpecTestResource {
+        self.integer_doubles = integer_doubles;
+        self
+    }
+
+    /// Sets which spellings of UUIDs are accepted in request bodies.
+    pub fn with_uuids(mut self, uuids: UuidPolicy) -> SpecTestResource {
+        self.uuids = uuids;
+        self
+    }
+
+    /// Serves at most `max_negatives` of each endpoint's negative body test cases, evenly spread
+    /// across them. The others keep their indices but fail without being served.
+    pub fn with_max_negatives(mut self, max_negatives: Option<usize>) -> SpecTestResource {
+        self.max_negatives = max_negatives;
+        self
+    }
+
+    /// Responds `406 Not Acceptable` to body test requests whose `Accept` header excludes the
+    /// content type of the test case, even where the response would have no body.
+    pub fn with_strict_accept(mut self, strict_accept: bool) -> SpecTestResource {
+        self.strict_accept = strict_accept;
+        self
+    }
+
+    /// Fails path param tests unless the client percent-encoded every character of the param
+    /// outside RFC 3986's unreserved set, e.g. sent a `/` in the value as `%2F`.
+    pub fn with_strict_path_encoding(mut self, strict_path_encoding: bool) -> SpecTestResource {
+        self.strict_path_encoding = strict_path_encoding;
+        self
+    }
+
+    /// Streams positive body test cases of list and set types as newline-delimited JSON, one
+    /// element per line, instead of as a single JSON array.
+    pub fn with_ndjson(mut self, ndjson: bool) -> SpecTestResource {
+        self.ndjson = ndjson;
+        self
     }
 
     /// Create a test that validates that some param from the request is as expected.
@@ -72,6 +179,10 @@ impl SpecTestResource {
     {
         move |resource: &SpecTestResource, request: &mut Request| -> Result<_> {
             let index = SpecTestResource::parse_index(request)?;
+            resource
+                .faults
+                .inject(&format!("param/{}/{}", endpoint, index), request)?;
+            resource.user_agent.check(request)?;
             let param_str = get_param(request)?;
             let validate =
                 |request: &mut Request| SpecTestResource::assert_no_request_body(request);
@@ -165,6 +276,10 @@ impl SpecTestResource {
         // Expects an index
         move |resource: &SpecTestResource, request: &mut Request| -> Result<Response> {
             let index: TestIndex = SpecTestResource::parse_index(request)?.into();
+            resource
+                .faults
+                .inject(&format!("body/{}/{}", endpoint, index), request)?;
+            resource.user_agent.check(request)?;
 
             // Perform all assertions in this block, because if they fail, we want to catch the
             // error and record it.
@@ -174,19 +289,88 @@ impl SpecTestResource {
             validate(request)?;
 
             let cases = get_endpoint(&resource.test_cases.auto_deserialize, &endpoint)?;
-            return get_test_case_at_index(cases, &index)?
+            let case = get_test_case_at_index(cases, &index, resource.max_negatives)?;
+            if resource.strict_accept {
+                resource.check_accept(request, &case)?;
+            }
+            return case
                 .map_left(|case| match &case.0.value {
                     ConjureValue::Primitive(ConjurePrimitiveValue::Binary(binary)) => {
                         StreamingResponse {
                             data: binary.0.to_owned(),
                         }.into_response(request)
                     }
+                    ConjureValue::List(values) if resource.ndjson => {
+                        NdjsonResponse::new(values)?.into_response(request)
+                    }
+                    ConjureValue::Set(values) if resource.ndjson => {
+                        NdjsonResponse::new(values)?.into_response(request)
+                    }
                     _ => SpecTestResource::response_non_streaming(case.0.text.as_str(), request),
                 }).map_right(|case| SpecTestResource::response_non_streaming(case.0, request))
                 .into_inner();
         }
     }
 
+    /// Fails unless the request accepts the content type that the test case is served with.
+    fn check_accept(
+        &self,
+        request: &Request,
+        case: &Either<AutoDeserializePositiveTest, AutoDeserializeNegativeTest>,
+    ) -> Result<()> {
+        let mime = match case {
+            Left(AutoDeserializePositiveTest(ResolvedTestCase {
+                value: ConjureValue::Primitive(ConjurePrimitiveValue::Binary(_)),
+                ..
+            })) => APPLICATION_OCTET_STREAM,
+            Left(AutoDeserializePositiveTest(ResolvedTestCase {
+                value: ConjureValue::List(_),
+                ..
+            }))
+            | Left(AutoDeserializePositiveTest(ResolvedTestCase {
+                value: ConjureValue::Set(_),
+                ..
+            }))
+                if self.ndjson =>
+            {
+                APPLICATION_NDJSON.parse().unwrap()
+            }
+            _ => APPLICATION_JSON,
+        };
+        request.response_format(&[MimeFormat(mime)])?;
+        Ok(())
+    }
+
+    /// Fails unless the named path param was sent with only unreserved characters and percent
+    /// escapes, checking the raw URI rather than the decoded value.
+    fn check_path_encoding(request: &Request, name: &str) -> Result<()> {
+        let raw_param = request
+            .raw_path_param(name)
+            .ok_or_else(|| Error::internal_safe("raw path params are not being tracked"))?;
+        let bytes = raw_param.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' if i + 2 < bytes.len()
+                    && bytes[i + 1].is_ascii_hexdigit()
+                    && bytes[i + 2].is_ascii_hexdigit() =>
+                {
+                    i += 3
+                }
+                b if b.is_ascii_alphanumeric() || b"-._~".contains(&b) => i += 1,
+                _ => {
+                    return Err(Error::new_safe(
+                        "path param contains a character that must be percent-encoded",
+                        VerificationError::UnencodedPathParam {
+                            raw_param: raw_param.to_string(),
+                        },
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn parse_index(request: &Request) -> Result<usize> {
         request
             .path_param("index")
@@ -196,6 +380,18 @@ impl SpecTestResource {
 
     /// Returns a `VerificationError::ConfirmationFailure` if the result is not what was expected.
     fn confirm(&self, request: &mut Request) -> Result<NoContent> {
+        self.confirm_body(request, false)
+    }
+
+    /// Like `confirm`, but for a partial update of an object: the body may leave out any fields,
+    /// and only the fields it sets must match the expected value. Fields it leaves out are treated
+    /// as absent rather than defaulted, so even required fields may be left out.
+    fn confirm_patch(&self, request: &mut Request) -> Result<NoContent> {
+        self.confirm_body(request, true)
+    }
+
+    fn confirm_body(&self, request: &mut Request, partial: bool) -> Result<NoContent> {
+        self.user_agent.check(request)?;
         let index: usize = SpecTestResource::parse_index(request)?;
         let endpoint = EndpointName::new(request.path_param("endpoint"));
 
@@ -219,10 +415,11 @@ impl SpecTestResource {
         // raw_body() and when to deserialize it to JSON.
 
         // Special handling for when body is empty - allow no content type (or otherwise expect JSON).
-        let request_body_value: serde_json::Value = if let Some(ContentLength(0)) = request
+        let content_length = request
             .headers()
             .typed_get::<ContentLength>()
-            .map_err(Error::internal_safe)?
+            .map_err(Error::internal_safe)?;
+        let request_body_value: serde_json::Value = if let Some(ContentLength(0)) = content_length
         {
             let mime_opt = request
                 .headers()
@@ -236,24 +433,36 @@ impl SpecTestResource {
                 ));
             };
             serde_json::Value::Null
+        } else if self.profile.skip_json_bom() {
+            request.body_skipping_bom()?
         } else {
             request.body()?
         };
-        let request_body = conjure_type.deserialize(&request_body_value).map_err(|e| {
-            let error_message = format!("{}", e);
-            Error::new_safe(
-                e,
-                VerificationError::confirmation_failure(
-                    &expected_body_str,
-                    expected_body,
-                    &request_body_value,
-                    None,
-                    error_message,
-                ),
-            )
-        })?;
+        if let (true, Some(ContentLength(length))) = (self.exact_body_reads, content_length) {
+            request.check_body_bytes_read(length)?;
+        }
+        let request_body = conjure_type
+            .with_profile(self.profile)
+            .with_forbid_null_optionals(self.forbid_null_optionals)
+            .with_integer_doubles(self.integer_doubles)
+            .with_uuids(self.uuids)
+            .with_partial(partial)
+            .deserialize(&request_body_value)
+            .map_err(|e| {
+                let error_message = format!("{}", e);
+                Error::new_safe(
+                    e,
+                    VerificationError::confirmation_failure(
+                        &expected_body_str,
+                        expected_body,
+                        &request_body_value,
+                        None,
+                        error_message,
+                    ),
+                )
+            })?;
         // Compare request_body with what the test case says we sent
-        if request_body != *expected_body {
+        if !SpecTestResource::body_matches(&request_body, expected_body, partial) {
             let error = "Body didn't match expected Conjure value";
             return Err(Error::new_safe(
                 error,
@@ -268,6 +477,23 @@ impl SpecTestResource {
         }
         Ok(NoContent)
     }
+
+    /// Whether the request body matches the expected value. A partial object only has to match
+    /// the fields it sets.
+    fn body_matches(
+        request_body: &ConjureValue,
+        expected_body: &ConjureValue,
+        partial: bool,
+    ) -> bool {
+        match (request_body, expected_body) {
+            (ConjureValue::Object(fields), ConjureValue::Object(expected_fields)) if partial => {
+                fields
+                    .iter()
+                    .all(|(name, value)| expected_fields.get(name) == Some(value))
+            }
+            _ => request_body == expected_body,
+        }
+    }
 }
 
 impl Resource for SpecTestResource {
@@ -294,6 +520,7 @@ pub struct AutoDeserializeNegativeTest<'a>(pub &'a str);
 fn get_test_case_at_index<'a>(
     cases: &'a ResolvedPositiveAndNegativeTestCases,
     index: &TestIndex,
+    max_negatives: Option<usize>,
 ) -> Result<Either<AutoDeserializePositiveTest<'a>, AutoDeserializeNegativeTest<'a>>> {
     let positives = cases.positive.test_cases.len();
     let negatives = cases.negative.len();
@@ -308,10 +535,19 @@ fn get_test_case_at_index<'a>(
     };
     let is_negative_test = index.0 >= positives;
     let result = if is_negative_test {
-        let test = cases
-            .negative
-            .get(index.0 - positives)
-            .ok_or_else(index_out_of_bounds)?;
+        let offset = index.0 - positives;
+        let test = cases.negative.get(offset).ok_or_else(index_out_of_bounds)?;
+        if let Some(max_negatives) = max_negatives {
+            if !is_sampled(offset, negatives, max_negatives) {
+                return Err(Error::new_safe(
+                    "Negative test case skipped",
+                    VerificationError::NegativeTestCaseSkipped {
+                        index: index.0,
+                        max_negatives,
+                    },
+                ));
+            }
+        }
         Right(test.as_str().into())
     } else {
         Left(cases.positive.test_cases[index.0].borrow().into())
@@ -319,6 +555,29 @@ fn get_test_case_at_index<'a>(
     Ok(result)
 }
 
+/// A response [Format] with a fixed content type.
+///
+/// [Format]: ../../conjure_verification_http/request/trait.Format.html
+struct MimeFormat(Mime);
+
+impl Format for MimeFormat {
+    fn mime(&self) -> &Mime {
+        &self.0
+    }
+}
+
+/// Whether the test case at `offset` is one of `max` test cases picked out of `len`, at evenly
+/// spaced offsets. The first test case is always picked.
+fn is_sampled(offset: usize, len: usize, max: usize) -> bool {
+    if len <= max {
+        return true;
+    }
+    // The picked offsets are `i * len / max` for `i` in `0..max`, so find the smallest `i` that
+    // reaches `offset` and check that it lands exactly on it.
+    let i = (offset * max + len - 1) / len;
+    i < max && i * len / max == offset
+}
+
 impl DynamicResource for SpecTestResource {
     fn register<R>(&self, router: &mut R)
     where
@@ -330,6 +589,11 @@ impl DynamicResource for SpecTestResource {
             "/confirm/:endpoint/:index",
             SpecTestResource::confirm,
         );
+        router.route_with_options(
+            Method::PATCH,
+            "/confirm/:endpoint/:index",
+            SpecTestResource::confirm_patch,
+        );
 
         // Wire up all automatic endpoint names.
         let automatic_endpoint_names = self.test_cases.auto_deserialize.keys();
@@ -343,14 +607,20 @@ impl DynamicResource for SpecTestResource {
         }
 
         for endpoint_name in self.test_cases.single_path_param_service.keys().cloned() {
+            let test = SpecTestResource::create_param_test(
+                endpoint_name.clone(),
+                |req| Ok(Some(req.path_param("param").into())),
+                |tests| &tests.single_path_param_service,
+            );
             router.route_with_options(
                 Method::POST,
                 format!("/single-path-param/{}/:index/:param", endpoint_name.0).as_str(),
-                SpecTestResource::create_param_test(
-                    endpoint_name,
-                    |req| Ok(Some(req.path_param("param").into())),
-                    |tests| &tests.single_path_param_service,
-                ),
+                move |resource: &SpecTestResource, request: &mut Request| {
+                    if resource.strict_path_encoding {
+                        SpecTestResource::check_path_encoding(request, "param")?;
+                    }
+                    test(resource, request)
+                },
             );
         }
 
@@ -372,7 +642,7 @@ impl DynamicResource for SpecTestResource {
                 format!("/single-header-param/{}/:index", endpoint_name.0).as_str(),
                 SpecTestResource::create_param_test(
                     endpoint_name,
-                    |req| match req.headers().get::<String>("Some-Header".into()).map(|hv| {
+                    |req| match req.headers().get(SINGLE_HEADER_NAME).map(|hv| {
                         hv.to_str()
                             .map(|s| s.to_string())
                             .map_err(|e| Error::new_safe(e, Code::InvalidArgument))
@@ -423,18 +693,25 @@ impl OrErr<(), Error> for bool {
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
+    use std::sync::atomic::AtomicUsize;
     use std::sync::Arc;
 
+    use futures::{Future, Stream};
+    use hyper::header::HeaderName;
     use hyper::header::HeaderValue;
+    use hyper::header::{ACCEPT, CONTENT_TYPE, USER_AGENT};
+    use hyper::service::Service;
     use hyper::HeaderMap;
     use hyper::Method;
-    use mime::APPLICATION_JSON;
+    use hyper::StatusCode;
     use typed_headers::{ContentType, HeaderMapExt};
 
     use conjure::ir;
     use conjure::resolved_type::builders::*;
     use conjure::resolved_type::OptionalType;
     use conjure::resolved_type::ResolvedType;
+    use conjure_verification_error::SerializableError;
+    use handler::HttpService;
     use register_resource;
     use resolved_test_cases;
     use router;
@@ -446,6 +723,7 @@ mod test {
     use super::*;
     use conjure_verification_common::type_mapping::builder::*;
     use conjure_verification_common::type_mapping::TestType;
+    use conjure_verification_http::response::Body;
 
     /// This exists because `Request` takes references only so it can't be used as a builder.
     #[derive(Clone, Default)]
@@ -454,6 +732,7 @@ mod test {
         path_params: HashMap<String, String>,
         query_params: HashMap<String, Vec<String>>,
         headers: HeaderMap<HeaderValue>,
+        body_bytes_read: Option<Arc<AtomicUsize>>,
     }
 
     impl RequestBuilder {
@@ -465,6 +744,9 @@ mod test {
                 &self.headers,
                 &mut body,
             );
+            if let Some(body_bytes_read) = self.body_bytes_read {
+                request = request.with_body_bytes_read(body_bytes_read);
+            }
             f(&mut request)
         }
     }
@@ -538,6 +820,69 @@ mod test {
         ).unwrap();
     }
 
+    #[test]
+    fn test_strict_path_encoding() {
+        let mut test_cases = ClientTestCases::default();
+        test_cases.single_path_param_service =
+            hashmap!(EndpointName::new("string") => vec!["\"a:b\"".into()]);
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::SinglePathParam,
+            EndpointName::new("string"),
+            primitive_type(ir::PrimitiveType::String),
+        );
+        let test_cases =
+            resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap();
+        let (router, _) = register(
+            SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                .with_strict_path_encoding(true),
+        );
+        // Go through the real routing so the raw param is exactly what the URI carried. A `:` is
+        // allowed unescaped in a path segment, so unlike `/` it reaches the handler either way.
+        let mut service = HttpService::new(Arc::new(router));
+        let mut send = |raw_param: &str| {
+            let uri = format!("/single-path-param/string/0/{}", raw_param);
+            let request = hyper::Request::post(uri.as_str()).body(hyper::Body::empty()).unwrap();
+            service.call(request).wait().unwrap()
+        };
+
+        assert!(send("a%3Ab").status().is_success());
+        assert!(send("a%3ab").status().is_success());
+        let response = send("a:b");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: SerializableError = serde_json::from_slice(&body).unwrap();
+        assert_eq!(error.name(), "ConjureVerification:UnencodedPathParam");
+    }
+
+    #[test]
+    fn test_header_case_insensitive() {
+        let router = setup_routes(|cases, types| {
+            cases.single_header_service = hashmap!(
+                EndpointName::new("string") => vec!["\"yo\"".into()]
+            );
+            types.add(
+                TestType::SingleHeaderParam,
+                EndpointName::new("string"),
+                ResolvedType::Primitive(ir::PrimitiveType::String),
+            );
+        });
+        for header_name in &["some-header", "SOME-HEADER", "sOmE-hEaDeR"] {
+            send_request(
+                &router,
+                Method::POST,
+                "/single-header-param/string/0",
+                0,
+                |req| {
+                    req.headers.insert(
+                        HeaderName::from_bytes(header_name.as_bytes()).unwrap(),
+                        "yo".parse().unwrap(),
+                    );
+                },
+            ).unwrap();
+        }
+    }
+
     #[test]
     fn test_query() {
         let router = setup_routes(|cases, types| {
@@ -618,6 +963,76 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_injected_failures() {
+        let (_, router, _) = setup_simple_auto_positive();
+        let fail_first = |req: &mut RequestBuilder| {
+            req.query_params.insert("failFirst".into(), vec!["2".into()]);
+        };
+
+        for _ in 0..2 {
+            match send_request(&router, Method::GET, "/body/foo/0", 0, fail_first) {
+                Err(err) => assert_eq!(err.code().http_error_code(), 503),
+                Ok(_) => panic!("Expected an injected failure"),
+            }
+        }
+        send_request(&router, Method::GET, "/body/foo/0", 0, fail_first).unwrap();
+        // Without the toggle, requests are never failed.
+        send_request(&router, Method::GET, "/body/foo/0", 0, |_| {}).unwrap();
+    }
+
+    #[test]
+    fn test_injected_failures_with_status() {
+        let (_, router, _) = setup_simple_auto_positive();
+        let fail_first = |req: &mut RequestBuilder| {
+            req.query_params.insert("failFirst".into(), vec!["1".into()]);
+            req.query_params.insert("failWith".into(), vec!["429".into()]);
+        };
+
+        match send_request(&router, Method::GET, "/body/foo/0", 0, fail_first) {
+            Err(err) => assert_eq!(err.code(), Code::TooManyRequests),
+            Ok(_) => panic!("Expected an injected failure"),
+        }
+        send_request(&router, Method::GET, "/body/foo/0", 0, fail_first).unwrap();
+    }
+
+    #[test]
+    fn test_user_agent_requirement() {
+        let (_, test_cases) = simple_auto_positive_test_cases();
+        let (router, _) = register(
+            SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                .with_user_agent_requirement(UserAgentRequirement::Conjure),
+        );
+        let with_user_agent = |user_agent: &'static str| {
+            move |req: &mut RequestBuilder| {
+                req.headers
+                    .insert(USER_AGENT, HeaderValue::from_static(user_agent));
+            }
+        };
+
+        match send_request(&router, Method::GET, "/body/foo/0", 0, |_| {}) {
+            Err(err) => assert_eq!(err.name(), "ConjureVerification:MissingUserAgent"),
+            Ok(_) => panic!("Request without a User-Agent didn't fail"),
+        }
+        match send_request(
+            &router,
+            Method::GET,
+            "/body/foo/0",
+            0,
+            with_user_agent("not a conjure agent"),
+        ) {
+            Err(err) => assert_eq!(err.name(), "ConjureVerification:InvalidUserAgent"),
+            Ok(_) => panic!("Request with an invalid User-Agent didn't fail"),
+        }
+        send_request(
+            &router,
+            Method::GET,
+            "/body/foo/0",
+            0,
+            with_user_agent("test-client/1.0.0"),
+        ).unwrap();
+    }
+
     #[test]
     fn test_confirm() {
         let (expected_body, router, _) = setup_simple_auto_positive();
@@ -628,6 +1043,322 @@ mod test {
         confirm_with(&router, expected_body.into(), None);
     }
 
+    #[test]
+    fn test_exact_body_reads() {
+        let (expected_body, test_cases) = simple_auto_positive_test_cases();
+        let (router, _) = register(
+            SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                .with_exact_body_reads(true),
+        );
+        let confirm = |bytes_read: usize| {
+            let endpoint = match router.route(&Method::POST, "/confirm/foo/0") {
+                RouteResult::Matched { endpoint, .. } => endpoint,
+                _ => panic!("Failed to route!"),
+            };
+            let mut builder = RequestBuilder::default();
+            builder.path_params = hashmap!("index" => "0", "endpoint" => "foo");
+            builder.headers.typed_insert(&ContentType(APPLICATION_JSON));
+            builder
+                .headers
+                .typed_insert(&ContentLength(expected_body.len() as u64));
+            builder.body = expected_body.into();
+            builder.body_bytes_read = Some(Arc::new(AtomicUsize::new(bytes_read)));
+            builder.with_request(|req| endpoint.handler.handle(req))
+        };
+
+        assert!(confirm(expected_body.len()).is_ok());
+        match confirm(expected_body.len() - 1) {
+            Err(err) => assert_eq!(err.name(), "Default:UnexpectedBodyBytesRead"),
+            Ok(_) => panic!("Confirmation with a partially read body didn't fail"),
+        }
+    }
+
+    #[test]
+    fn test_forbid_null_optionals() {
+        let resource = |forbid_null_optionals| {
+            register(
+                SpecTestResource::new(
+                    Box::new(optional_field_test_cases()),
+                    StrictnessProfile::Server,
+                ).with_forbid_null_optionals(forbid_null_optionals),
+            ).0
+        };
+
+        let lenient = resource(false);
+        confirm_with(&lenient, "{}".into(), None);
+        confirm_with(&lenient, "{\"heyo\": null}".into(), None);
+
+        let strict = resource(true);
+        confirm_with(&strict, "{}".into(), None);
+        let error = confirm_with(
+            &strict,
+            "{\"heyo\": null}".into(),
+            Some(Code::InvalidArgument),
+        ).unwrap();
+        assert!(error.safe_params()["cause"].contains("omitted, not null"));
+    }
+
+    #[test]
+    fn test_confirm_patch() {
+        let mut test_cases = ClientTestCases::default();
+        test_cases.auto_deserialize = hashmap!(
+            EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                positive: vec![r#"{"a": 1, "b": "x", "c": 2}"#.to_string()],
+                negative: vec![],
+            }
+        );
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::Body,
+            EndpointName::new("foo"),
+            object_definition(
+                "Name",
+                &[
+                    field_definition("a", primitive_type(ir::PrimitiveType::Integer)),
+                    field_definition("b", primitive_type(ir::PrimitiveType::String)),
+                    field_definition(
+                        "c",
+                        optional_type(primitive_type(ir::PrimitiveType::Integer)),
+                    ),
+                ],
+            ),
+        );
+        let (router, _) = create_resource(
+            resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap(),
+        );
+        let patch = |body: &str, expected_error| {
+            confirm_with_method(&router, Method::PATCH, body.into(), expected_error)
+        };
+
+        // A full body must set every required field.
+        confirm_with(&router, r#"{"a": 1, "b": "x", "c": 2}"#.into(), None);
+        confirm_with(&router, r#"{"a": 1}"#.into(), Some(Code::InvalidArgument));
+
+        // A partial update only has to match the fields it sets.
+        patch(r#"{"a": 1, "b": "x", "c": 2}"#, None);
+        patch(r#"{"a": 1}"#, None);
+        patch(r#"{"c": 2}"#, None);
+        patch("{}", None);
+        let error = patch(r#"{"a": 2}"#, Some(Code::InvalidArgument)).unwrap();
+        assert_eq!(error.name(), "ConjureVerification:ConfirmationFailure");
+        // An explicit null is an absent optional, which doesn't match.
+        patch(r#"{"c": null}"#, Some(Code::InvalidArgument));
+        // Set fields are still validated against the type.
+        patch(r#"{"a": "x"}"#, Some(Code::InvalidArgument));
+        patch(r#"{"d": 1}"#, Some(Code::InvalidArgument));
+    }
+
+    #[test]
+    fn test_canonical_uuids() {
+        let canonical = "\"7cd6e2f7-a2a6-4b9b-9b0c-0e4a2b6e9a51\"";
+        let uppercase = "\"7CD6E2F7-A2A6-4B9B-9B0C-0E4A2B6E9A51\"";
+        let resource = |uuids| {
+            let mut test_cases = ClientTestCases::default();
+            test_cases.auto_deserialize = hashmap!(
+                EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                    positive: vec![canonical.to_string()],
+                    negative: vec![],
+                }
+            );
+            let mut param_types = ParamTypesBuilder::default();
+            param_types.add(
+                TestType::Body,
+                EndpointName::new("foo"),
+                primitive_type(ir::PrimitiveType::Uuid),
+            );
+            let test_cases =
+                resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases)
+                    .unwrap();
+            register(
+                SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                    .with_uuids(uuids),
+            ).0
+        };
+
+        let lenient = resource(UuidPolicy::Lenient);
+        confirm_with(&lenient, canonical.into(), None);
+        confirm_with(&lenient, uppercase.into(), None);
+
+        let strict = resource(UuidPolicy::Canonical);
+        confirm_with(&strict, canonical.into(), None);
+        let error = confirm_with(&strict, uppercase.into(), Some(Code::InvalidArgument)).unwrap();
+        assert!(error.safe_params()["cause"].contains("not in canonical form"));
+    }
+
+    #[test]
+    fn test_strict_integer_doubles() {
+        let resource = |integer_doubles| {
+            let mut test_cases = ClientTestCases::default();
+            test_cases.auto_deserialize = hashmap!(
+                EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                    positive: vec!["9007199254740992".to_string()],
+                    negative: vec![],
+                }
+            );
+            let mut param_types = ParamTypesBuilder::default();
+            param_types.add(
+                TestType::Body,
+                EndpointName::new("foo"),
+                primitive_type(ir::PrimitiveType::Double),
+            );
+            let test_cases =
+                resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases)
+                    .unwrap();
+            register(
+                SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                    .with_integer_doubles(integer_doubles),
+            ).0
+        };
+        // 2^53 + 1 rounds to the test case's 2^53 as a double.
+        let imprecise = "9007199254740993";
+
+        let lenient = resource(IntegerDoublePolicy::Lenient);
+        confirm_with(&lenient, imprecise.into(), None);
+
+        let strict = resource(IntegerDoublePolicy::Strict);
+        confirm_with(&strict, "9007199254740992".into(), None);
+        let error = confirm_with(&strict, imprecise.into(), Some(Code::InvalidArgument)).unwrap();
+        assert!(error.safe_params()["cause"].contains("not exactly representable"));
+    }
+
+    #[test]
+    fn test_bom_prefixed_body() {
+        let resource = |profile| {
+            register(SpecTestResource::new(
+                Box::new(simple_auto_positive_test_cases().1),
+                profile,
+            )).0
+        };
+        let body = b"\xEF\xBB\xBF{\"heyo\": 5}".to_vec();
+
+        let strict = resource(StrictnessProfile::Server);
+        let error = confirm_with(&strict, body.clone(), Some(Code::InvalidArgument)).unwrap();
+        assert_eq!(error.name(), "Default:InvalidRequestBody");
+
+        let lenient = resource(StrictnessProfile::Client);
+        confirm_with(&lenient, body, None);
+        confirm_with(&lenient, "{\"heyo\": 5}".into(), None);
+    }
+
+    #[test]
+    fn test_max_negatives() {
+        let mut test_cases = ClientTestCases::default();
+        test_cases.auto_deserialize = hashmap!(
+            EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                positive: vec!["1".to_string()],
+                negative: (0..10).map(|i| format!("\"{}\"", i)).collect(),
+            }
+        );
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::Body,
+            EndpointName::new("foo"),
+            primitive_type(ir::PrimitiveType::Integer),
+        );
+        let param_types = param_types.build();
+        let resource = |max_negatives| {
+            let test_cases =
+                resolved_test_cases::resolve_test_cases(&param_types, &test_cases).unwrap();
+            register(
+                SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                    .with_max_negatives(max_negatives),
+            ).0
+        };
+        let served = |router: &Router| {
+            (0..11)
+                .filter(|index| {
+                    let path = format!("/body/foo/{}", index);
+                    match send_request(router, Method::GET, &path, *index, |_| {}) {
+                        Ok(_) => true,
+                        Err(e) => {
+                            assert_eq!(e.name(), "ConjureVerification:NegativeTestCaseSkipped");
+                            false
+                        }
+                    }
+                }).collect::<Vec<_>>()
+        };
+
+        assert_eq!(served(&resource(None)), (0..11).collect::<Vec<_>>());
+        assert_eq!(served(&resource(Some(20))), (0..11).collect::<Vec<_>>());
+        assert_eq!(served(&resource(Some(3))), vec![0, 1, 4, 7]);
+        assert_eq!(served(&resource(Some(0))), vec![0]);
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let get = |router: &Router| send_request(router, Method::GET, "/body/foo/0", 0, |_| {});
+
+        let (router, _) = create_resource(list_body_test_cases());
+        let response = get(&router).unwrap();
+        assert_eq!(response.headers[CONTENT_TYPE], "application/json");
+
+        let (router, _) = register(
+            SpecTestResource::new(Box::new(list_body_test_cases()), StrictnessProfile::Server)
+                .with_ndjson(true),
+        );
+        let response = get(&router).unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers[CONTENT_TYPE], "application/x-ndjson");
+        let mut body = vec![];
+        match response.body {
+            Body::Streaming(mut writer) => writer.write_body(&mut body).unwrap(),
+            _ => panic!("Expected a streaming body"),
+        }
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "{\"id\":1}\n{\"id\":2}\n{\"id\":3}\n"
+        );
+    }
+
+    #[test]
+    fn test_strict_accept() {
+        let get = |router: &Router, accept: &'static str| {
+            send_request(router, Method::GET, "/body/foo/0", 0, |req| {
+                req.headers.insert(ACCEPT, HeaderValue::from_static(accept));
+            })
+        };
+        let not_acceptable = |result: Result<Response>| match result {
+            Ok(_) => panic!("Expected a Not Acceptable error"),
+            Err(e) => assert_eq!(e.code(), Code::NotAcceptable),
+        };
+        let strict = |test_cases| {
+            register(
+                SpecTestResource::new(Box::new(test_cases), StrictnessProfile::Server)
+                    .with_strict_accept(true),
+            ).0
+        };
+
+        let (_, router, _) = setup_simple_auto_positive();
+        not_acceptable(get(&router, "text/plain"));
+        let router = strict(simple_auto_positive_test_cases().1);
+        not_acceptable(get(&router, "text/plain"));
+        assert_eq!(
+            get(&router, "application/json").unwrap().status,
+            StatusCode::OK
+        );
+
+        // An empty optional is served without a body, so is only rejected in strict mode.
+        let (router, _) = create_resource(null_optional_body_test_cases());
+        assert_eq!(
+            get(&router, "text/plain").unwrap().status,
+            StatusCode::NO_CONTENT
+        );
+        let router = strict(null_optional_body_test_cases());
+        not_acceptable(get(&router, "text/plain"));
+        assert_eq!(
+            get(&router, "application/json").unwrap().status,
+            StatusCode::NO_CONTENT
+        );
+
+        // Binary is served as application/octet-stream.
+        let router = strict(simple_auto_positive_binary_test_cases().1);
+        not_acceptable(get(&router, "application/json"));
+        assert_eq!(
+            get(&router, "application/octet-stream").unwrap().status,
+            StatusCode::OK
+        );
+    }
+
     #[test]
     fn test_confirm_binary() {
         let (expected_body, router, _) = setup_simple_auto_positive_binary();
@@ -638,17 +1369,51 @@ mod test {
         confirm_with(&router, expected_body.into(), None);
     }
 
-    fn confirm_with(router: &Router, body: Vec<u8>, expected_error: Option<Code>) -> () {
-        if let RouteResult::Matched { endpoint, .. } = router.route(&Method::POST, "/confirm/foo/0")
-        {
+    #[test]
+    fn test_confirm_failure_renders_json() {
+        let (_, router, _) = setup_simple_auto_positive();
+        let error = confirm_with(&router, "{\"heyo\": 6}".into(), Some(Code::InvalidArgument))
+            .unwrap();
+
+        let params = error.safe_params();
+        let expected: serde_json::Value =
+            serde_json::from_str(&params["expectedBodyConjure"]).unwrap();
+        assert_eq!(expected, json!({"heyo": 5}));
+        let actual: serde_json::Value =
+            serde_json::from_str(&params["requestBodyConjure"]).unwrap();
+        assert_eq!(actual, json!({"heyo": 6}));
+    }
+
+    fn confirm_with(
+        router: &Router,
+        body: Vec<u8>,
+        expected_error: Option<Code>,
+    ) -> Option<Error> {
+        confirm_with_method(router, Method::POST, body, expected_error)
+    }
+
+    fn confirm_with_method(
+        router: &Router,
+        method: Method,
+        body: Vec<u8>,
+        expected_error: Option<Code>,
+    ) -> Option<Error> {
+        if let RouteResult::Matched { endpoint, .. } = router.route(&method, "/confirm/foo/0") {
             let mut builder = RequestBuilder::default();
             builder.path_params = hashmap!("index" => "0", "endpoint" => "foo");
             builder.headers.typed_insert(&ContentType(APPLICATION_JSON));
             builder.body = body;
             let result: Result<Response> = builder.with_request(|req| endpoint.handler.handle(req));
             match expected_error {
-                Some(code) => assert_eq!(result.err().unwrap().code(), code),
-                None => assert!(result.is_ok()),
+                Some(code) => {
+                    let error = result.err().unwrap();
+                    assert_eq!(error.code(), code);
+                    Some(error)
+                }
+                None => {
+                    assert!(result.is_ok());
+                    None
+                }
             }
         } else {
             panic!("Failed to route!")
@@ -693,6 +1458,12 @@ mod test {
     }
 
     fn setup_simple_auto_positive_binary() -> (&'static str, Router, Arc<SpecTestResource>) {
+        let (expected_body, test_cases) = simple_auto_positive_binary_test_cases();
+        let (router, resource) = create_resource(test_cases);
+        (expected_body, router, resource)
+    }
+
+    fn simple_auto_positive_binary_test_cases() -> (&'static str, ResolvedClientTestCases) {
         let expected_body = "\"YpbKYYSbQpCjvb754goTMpXaxVX/M2m2287jcpZ3vHI=\"";
         let mut test_cases = ClientTestCases::default();
         test_cases.auto_deserialize = hashmap!(
@@ -709,11 +1480,57 @@ mod test {
         );
         let resolved_test_cases =
             resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap();
-        let (router, resource) = create_resource(resolved_test_cases);
-        (expected_body, router, resource)
+        (expected_body, resolved_test_cases)
+    }
+
+    /// A single positive body test case, `null`, for an optional.
+    fn null_optional_body_test_cases() -> ResolvedClientTestCases {
+        let mut test_cases = ClientTestCases::default();
+        test_cases.auto_deserialize = hashmap!(
+            EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                positive: vec!["null".to_string()],
+                negative: vec![],
+            }
+        );
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::Body,
+            EndpointName::new("foo"),
+            optional_type(primitive_type(ir::PrimitiveType::Integer)),
+        );
+        resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap()
+    }
+
+    fn list_body_test_cases() -> ResolvedClientTestCases {
+        let mut test_cases = ClientTestCases::default();
+        test_cases.auto_deserialize = hashmap!(
+            EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                positive: vec!["[{\"id\": 1}, {\"id\": 2}, {\"id\": 3}]".to_string()],
+                negative: vec![],
+            }
+        );
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::Body,
+            EndpointName::new("foo"),
+            list_type(object_definition(
+                "Item",
+                &[field_definition(
+                    "id",
+                    primitive_type(ir::PrimitiveType::Integer),
+                )],
+            )),
+        );
+        resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap()
     }
 
     fn setup_simple_auto_positive() -> (&'static str, Router, Arc<SpecTestResource>) {
+        let (expected_body, test_cases) = simple_auto_positive_test_cases();
+        let (router, resource) = create_resource(test_cases);
+        (expected_body, router, resource)
+    }
+
+    fn simple_auto_positive_test_cases() -> (&'static str, ResolvedClientTestCases) {
         let expected_body = "{\"heyo\": 5}";
         let mut test_cases = ClientTestCases::default();
         test_cases.auto_deserialize = hashmap!(
@@ -736,13 +1553,43 @@ mod test {
         );
         let resolved_test_cases =
             resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap();
-        let (router, resource) = create_resource(resolved_test_cases);
-        (expected_body, router, resource)
+        (expected_body, resolved_test_cases)
+    }
+
+    /// A single positive body test case, `{}`, for an object with an optional field.
+    fn optional_field_test_cases() -> ResolvedClientTestCases {
+        let mut test_cases = ClientTestCases::default();
+        test_cases.auto_deserialize = hashmap!(
+            EndpointName::new("foo") => PositiveAndNegativeTestCases {
+                positive: vec!["{}".to_string()],
+                negative: vec![],
+            }
+        );
+        let mut param_types = ParamTypesBuilder::default();
+        param_types.add(
+            TestType::Body,
+            EndpointName::new("foo"),
+            object_definition(
+                "Name",
+                &[field_definition(
+                    "heyo",
+                    optional_type(primitive_type(ir::PrimitiveType::Integer)),
+                )],
+            ),
+        );
+        resolved_test_cases::resolve_test_cases(&param_types.build(), &test_cases).unwrap()
     }
 
     fn create_resource(test_cases: ResolvedClientTestCases) -> (Router, Arc<SpecTestResource>) {
+        register(SpecTestResource::new(
+            Box::new(test_cases),
+            StrictnessProfile::Server,
+        ))
+    }
+
+    fn register(resource: SpecTestResource) -> (Router, Arc<SpecTestResource>) {
         let mut builder = router::Router::builder();
-        let resource = Arc::new(SpecTestResource::new(Box::new(test_cases)));
+        let resource = Arc::new(resource);
         register_resource(&mut builder, &resource);
         (builder.build(), resource)
     }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use conjure_verification_common::conjure::ir::Conjure;
+use consistency::ConsistencyMode;
 use serde_json;
 use std::fs::File;
 use std::path::Path;
@@ -31,5 +32,5 @@ fn can_parse_all_test_cases() {
     let ir_file = File::open(Path::new(CONJURE_IR_PATH)).unwrap();
     let ir: Conjure = serde_json::from_reader(ir_file).unwrap();
 
-    ::resolve_test_cases(&ir, &test_cases.client).unwrap();
+    ::resolve_test_cases(&ir, &test_cases.client, ConsistencyMode::Error).unwrap();
 }
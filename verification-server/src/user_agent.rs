@@ -0,0 +1,142 @@
+// (c) Copyright 2018 Palantir Technologies Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that clients under test identify themselves with a `User-Agent` header.
+
+use conjure_verification_error::Error;
+use conjure_verification_error::Result;
+use conjure_verification_http::request::Request;
+use errors::VerificationError;
+use hyper::header::USER_AGENT;
+use std::str::FromStr;
+
+/// What a request's `User-Agent` header must look like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAgentRequirement {
+    /// The header is not checked.
+    None,
+    /// The header must be present and non-empty.
+    Present,
+    /// The header must be in the Conjure format, e.g. `my-service/1.2.3 (nodeId:foo) lib/0.1`.
+    Conjure,
+}
+
+impl Default for UserAgentRequirement {
+    fn default() -> UserAgentRequirement {
+        UserAgentRequirement::None
+    }
+}
+
+impl FromStr for UserAgentRequirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<UserAgentRequirement, String> {
+        match s {
+            "none" => Ok(UserAgentRequirement::None),
+            "present" => Ok(UserAgentRequirement::Present),
+            "conjure" => Ok(UserAgentRequirement::Conjure),
+            other => Err(format!(
+                "Unknown user agent requirement '{}', expected 'none', 'present' or 'conjure'",
+                other
+            )),
+        }
+    }
+}
+
+impl UserAgentRequirement {
+    /// Returns an error if the request's `User-Agent` header doesn't meet the requirement.
+    pub fn check(self, request: &Request) -> Result<()> {
+        if self == UserAgentRequirement::None {
+            return Ok(());
+        }
+
+        let user_agent = match request.headers().get(USER_AGENT) {
+            Some(value) => value
+                .to_str()
+                .map_err(|e| {
+                    Error::new_safe(
+                        e,
+                        VerificationError::InvalidUserAgent {
+                            user_agent: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                        },
+                    )
+                })?.trim(),
+            None => "",
+        };
+        if user_agent.is_empty() {
+            return Err(Error::new_safe(
+                "Request is missing a User-Agent",
+                VerificationError::MissingUserAgent,
+            ));
+        }
+        if self == UserAgentRequirement::Conjure && !is_conjure_user_agent(user_agent) {
+            return Err(Error::new_safe(
+                "User-Agent is not in the Conjure format",
+                VerificationError::InvalidUserAgent {
+                    user_agent: user_agent.to_string(),
+                },
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A Conjure user agent is a space separated list of `name/version` agents, the first of which may
+/// be followed by a parenthesized comment such as `(nodeId:foo)`.
+fn is_conjure_user_agent(user_agent: &str) -> bool {
+    let mut parts = user_agent.split_whitespace().peekable();
+    if !parts.next().map_or(false, is_agent) {
+        return false;
+    }
+    if parts
+        .peek()
+        .map_or(false, |part| part.starts_with('(') && part.ends_with(')'))
+    {
+        parts.next();
+    }
+    parts.all(is_agent)
+}
+
+fn is_agent(agent: &str) -> bool {
+    let mut split = agent.splitn(2, '/');
+    let name = split.next().unwrap_or("");
+    let version = split.next().unwrap_or("");
+
+    let mut name_chars = name.chars();
+    name_chars.next().map_or(false, |c| c.is_ascii_alphabetic())
+        && name_chars.all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && version.chars().next().map_or(false, |c| c.is_ascii_digit())
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn conjure_format() {
+        assert!(is_conjure_user_agent("my-service/1.2.3"));
+        assert!(is_conjure_user_agent("my-service/1.2.3 (nodeId:foo) lib/0.1.0-rc1"));
+        assert!(is_conjure_user_agent("my-service/1.2.3 lib/0.1.0"));
+
+        assert!(!is_conjure_user_agent(""));
+        assert!(!is_conjure_user_agent("my-service"));
+        assert!(!is_conjure_user_agent("my-service/"));
+        assert!(!is_conjure_user_agent("1service/1.0"));
+        assert!(!is_conjure_user_agent("(nodeId:foo) my-service/1.2.3"));
+        assert!(!is_conjure_user_agent("Mozilla/5.0 (X11; Linux x86_64)"));
+    }
+}